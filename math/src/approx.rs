@@ -1,3 +1,5 @@
+pub const EPSILON: f32 = 1e-4;
+
 pub trait Approx {
     fn is_near(&self, other: &Self, eps: f32) -> bool;
 }
@@ -0,0 +1,228 @@
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::point::point;
+use crate::uv::spherical_map;
+use crate::vector::{vector, Vector};
+use image::codecs::hdr::HdrDecoder;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::BufReader;
+
+/// An equirectangular (latitude-longitude) image of the surroundings,
+/// looked up by direction. Each pixel covers a patch of constant color.
+///
+/// The top row of the image is straight up and the bottom row straight
+/// down; the middle column is in the +z direction, like `spherical_map`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EnvironmentMap {
+    pub canvas: Canvas,
+}
+
+impl EnvironmentMap {
+    pub fn new(canvas: Canvas) -> Result<EnvironmentMap, &'static str> {
+        if canvas.width == 0 || canvas.height == 0 {
+            return Err("Image has no pixels");
+        }
+        Ok(EnvironmentMap { canvas })
+    }
+
+    /// Loads an image file. Radiance `.hdr` files keep their full range;
+    /// other formats, e.g. PNG, are read through the `image` crate and
+    /// scaled to `[0, 1]`, in the encoding `Image` writes them with.
+    pub fn read(filename: &str) -> Result<EnvironmentMap, String> {
+        let error = |e: image::ImageError| format!("{}: {}", filename, e);
+        let mut canvas;
+        if filename.to_lowercase().ends_with(".hdr") {
+            let file = File::open(filename).map_err(|e| format!("{}: {}", filename, e))?;
+            let decoder = HdrDecoder::new(BufReader::new(file)).map_err(error)?;
+            let meta = decoder.metadata();
+            let (width, height) = (meta.width as usize, meta.height as usize);
+            let pixels = decoder.read_image_hdr().map_err(error)?;
+            canvas = Canvas::new(width, height, Color::black());
+            for (i, pixel) in pixels.iter().enumerate() {
+                let [r, g, b] = pixel.0;
+                canvas.set(i % width, i / width, Color::new(r, g, b));
+            }
+        } else {
+            let img = image::open(filename).map_err(error)?.to_rgb32f();
+            canvas = Canvas::new(img.width() as usize, img.height() as usize, Color::black());
+            for (x, y, pixel) in img.enumerate_pixels() {
+                let [r, g, b] = pixel.0;
+                canvas.set(x as usize, y as usize, Color::new(r, g, b));
+            }
+        }
+        EnvironmentMap::new(canvas).map_err(|e| format!("{}: {}", filename, e))
+    }
+
+    /// Returns the coordinates of a direction in the image, both in `[0, 1]`
+    /// with `(0, 0)` at the top left.
+    pub fn direction_to_uv(direction: Vector) -> (f32, f32) {
+        let (u, v) = spherical_map(point(direction.x, direction.y, direction.z));
+        (u, 1.0 - v)
+    }
+
+    /// The inverse of `direction_to_uv`, returns a unit vector.
+    pub fn uv_to_direction(u: f32, v: f32) -> Vector {
+        let phi = v * PI;
+        let theta = (0.5 - u) * 2.0 * PI;
+        vector(phi.sin() * theta.sin(), phi.cos(), phi.sin() * theta.cos())
+    }
+
+    /// Returns the pixel that a direction falls in.
+    pub fn pixel_for(&self, direction: Vector) -> (usize, usize) {
+        let (u, v) = EnvironmentMap::direction_to_uv(direction);
+        let x = ((u * self.canvas.width as f32) as usize).min(self.canvas.width - 1);
+        let y = ((v * self.canvas.height as f32) as usize).min(self.canvas.height - 1);
+        (x, y)
+    }
+
+    pub fn color_for(&self, direction: Vector) -> Color {
+        let (x, y) = self.pixel_for(direction);
+        self.canvas.get(x, y)
+    }
+}
+
+/// What rays that miss every object of a `World` see.
+#[derive(Debug, PartialEq)]
+pub enum Background {
+    Color(Color),
+    /// Blends from `bottom` for rays going straight down to `top` for rays
+    /// going straight up.
+    Gradient {
+        bottom: Color,
+        top: Color,
+    },
+    Environment(EnvironmentMap),
+}
+
+impl Background {
+    pub fn color_for(&self, direction: Vector) -> Color {
+        match self {
+            Background::Color(c) => *c,
+            Background::Gradient { bottom, top } => {
+                let t = (direction.normalized().y + 1.0) / 2.0;
+                *bottom * (1.0 - t) + *top * t
+            }
+            Background::Environment(map) => map.color_for(direction),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{Image, RGB};
+    use crate::random::Rng;
+    use crate::ray::Ray;
+    use crate::test_utils::*;
+    use crate::world::{World, MAX_DEPTH};
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    #[test]
+    fn test_constant_background() {
+        let bg = Background::Color(Color::new(0.1, 0.2, 0.3));
+        assert_eq!(bg.color_for(vector(0, 1, 0)), Color::new(0.1, 0.2, 0.3));
+        assert_eq!(bg.color_for(vector(1, -1, 0)), Color::new(0.1, 0.2, 0.3));
+    }
+
+    #[test]
+    fn test_gradient_background() {
+        let bg = Background::Gradient {
+            bottom: Color::new(1.0, 1.0, 1.0),
+            top: Color::new(0.0, 0.5, 1.0),
+        };
+        assert_near!(bg.color_for(vector(0, 1, 0)), Color::new(0.0, 0.5, 1.0));
+        assert_near!(bg.color_for(vector(0, -3, 0)), Color::new(1.0, 1.0, 1.0));
+        assert_near!(bg.color_for(vector(1, 0, 0)), Color::new(0.5, 0.75, 1.0));
+    }
+
+    #[test]
+    fn test_direction_to_uv() {
+        let cases = [
+            (vector(0, 0, 1), (0.5, 0.5)),
+            (vector(1, 0, 0), (0.25, 0.5)),
+            (vector(-1, 0, 0), (0.75, 0.5)),
+            (vector(0.0, FRAC_1_SQRT_2, FRAC_1_SQRT_2), (0.5, 0.25)),
+            (vector(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2), (0.5, 0.75)),
+        ];
+        for (d, (u, v)) in cases {
+            let (du, dv) = EnvironmentMap::direction_to_uv(d);
+            assert_near!(du, u);
+            assert_near!(dv, v);
+        }
+    }
+
+    #[test]
+    fn test_uv_to_direction_is_inverse() {
+        for (u, v) in [(0.5, 0.5), (0.1, 0.2), (0.8, 0.9), (0.3, 0.6)] {
+            let d = EnvironmentMap::uv_to_direction(u, v);
+            assert_near!(d.len(), 1.0);
+            let (du, dv) = EnvironmentMap::direction_to_uv(d);
+            assert_near!(du, u, 1e-4);
+            assert_near!(dv, v, 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_environment_map_lookup() {
+        let mut canvas = Canvas::new(4, 2, Color::black());
+        canvas.set(2, 0, Color::new(1.0, 0.0, 0.0));
+        canvas.set(1, 1, Color::new(0.0, 1.0, 0.0));
+        let bg = Background::Environment(EnvironmentMap::new(canvas).unwrap());
+        assert_eq!(
+            bg.color_for(vector(-0.1, 1.0, 1.0)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            bg.color_for(vector(1.0, -1.0, 0.1)),
+            Color::new(0.0, 1.0, 0.0)
+        );
+        assert_eq!(bg.color_for(vector(0, 0, -1)), Color::black());
+    }
+
+    #[test]
+    fn test_empty_environment_is_rejected() {
+        assert!(EnvironmentMap::new(Canvas::new(0, 0, Color::black())).is_err());
+        assert!(EnvironmentMap::new(Canvas::new(4, 0, Color::black())).is_err());
+    }
+
+    #[test]
+    fn test_read_missing_environment() {
+        assert!(EnvironmentMap::read("does/not/exist.hdr").is_err());
+    }
+
+    #[test]
+    fn test_read_hdr_environment() {
+        let path = std::env::temp_dir().join("background_test_read_hdr_environment.hdr");
+        let pixels = [
+            image::Rgb([4.0f32, 0.5, 0.25]),
+            image::Rgb([0.0, 0.0, 16.0]),
+        ];
+        let file = std::fs::File::create(&path).unwrap();
+        image::codecs::hdr::HdrEncoder::new(file)
+            .encode(&pixels, 2, 1)
+            .unwrap();
+        let map = EnvironmentMap::read(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(map.canvas.width, 2);
+        assert_near!(map.canvas.get(0, 0), Color::new(4.0, 0.5, 0.25), 1e-2);
+        assert_near!(map.canvas.get(1, 0), Color::new(0.0, 0.0, 16.0), 1e-2);
+    }
+
+    #[test]
+    fn test_png_environment_round_trip() {
+        let path = std::env::temp_dir().join("background_test_png_environment_round_trip.png");
+        let path = path.to_str().unwrap();
+        let rgb = RGB::from_u8(128, 64, 200);
+        let mut source = Image::new(2, 1, RGB::black());
+        source.set(1, 0, rgb);
+        source.write_png(path);
+        let mut w = World::new();
+        w.background = Background::Environment(EnvironmentMap::read(path).unwrap());
+        std::fs::remove_file(path).unwrap();
+        // A ray missing everything sees the sky as it was in the file.
+        let r = Ray::new(point(0, 0, 0), vector(0, 0, 1));
+        let canvas = Canvas::new(1, 1, w.color_at(&r, MAX_DEPTH, &mut Rng::new(0)));
+        assert_eq!(Image::from_canvas(canvas).get(0, 0), rgb);
+    }
+}
@@ -0,0 +1,82 @@
+use math::camera::Camera;
+use math::intersect::{hit, Intersection};
+use math::point::point;
+use math::ray::Ray;
+use math::sphere::Sphere;
+use math::transforms::{scaling, translation};
+use math::vector::vector;
+use math::view::view_transform;
+use math::world::World;
+use std::f32::consts::PI;
+use std::time::Instant;
+
+const FIELD_SIZE: usize = 60;
+const SPACING: f32 = 2.5;
+const WIDTH: usize = 160;
+const HEIGHT: usize = 120;
+
+fn brute_force_hit<'a>(w: &'a World, r: &Ray) -> Option<Intersection<'a>> {
+    let mut xs = vec![];
+    for object in w.objects() {
+        xs.extend(object.intersect(r));
+    }
+    hit(&xs)
+}
+
+fn main() {
+    let mut w = World::new();
+    let offset = SPACING * (FIELD_SIZE - 1) as f32 / 2.0;
+    for i in 0..FIELD_SIZE {
+        for j in 0..FIELD_SIZE {
+            let mut s = Sphere::new();
+            s.set_transform(
+                &translation(
+                    i as f32 * SPACING - offset,
+                    0.0,
+                    j as f32 * SPACING - offset,
+                ) * &scaling(0.8, 0.8, 0.8),
+            )
+            .unwrap();
+            w.add_object(Box::new(s));
+        }
+    }
+
+    let mut camera = Camera::new(WIDTH, HEIGHT, PI / 3.0);
+    camera
+        .set_transform(view_transform(
+            point(0.0, 30.0, -offset - 20.0),
+            point(0, 0, 0),
+            vector(0, 1, 0),
+        ))
+        .unwrap();
+    let rays: Vec<Ray> = (0..HEIGHT)
+        .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+        .map(|(x, y)| camera.ray_for_pixel(x, y))
+        .collect();
+
+    let start = Instant::now();
+    let expected: Vec<Option<f32>> = rays
+        .iter()
+        .map(|r| brute_force_hit(&w, r).map(|x| x.t))
+        .collect();
+    let brute_force = start.elapsed();
+
+    // The first call builds the hierarchy, so it is timed separately.
+    let start = Instant::now();
+    w.hit(&rays[0]);
+    let build = start.elapsed();
+
+    let start = Instant::now();
+    let actual: Vec<Option<f32>> = rays.iter().map(|r| w.hit(r).map(|x| x.t)).collect();
+    let bvh = start.elapsed();
+
+    assert_eq!(expected, actual);
+    println!("{} spheres, {} rays", w.objects().len(), rays.len());
+    println!("brute force: {:?}", brute_force);
+    println!("bvh build:   {:?}", build);
+    println!("bvh:         {:?}", bvh);
+    println!(
+        "speedup:     {:.1}x",
+        brute_force.as_secs_f32() / bvh.as_secs_f32()
+    );
+}
@@ -4,10 +4,10 @@ use math::image::Image;
 use math::intersect::hit;
 use math::point::Point;
 use math::ray::Ray;
+use math::shape::Shape;
 use math::sphere::Sphere;
 use math::tuple::Tuple;
 use math::vector::Vector;
-use math::world::{Intersect, WorldObject};
 
 const SIZE: usize = 500;
 const WALL_Z: f32 = 10.0;
@@ -17,7 +17,7 @@ const PIXEL_SIZE: f32 = WALL_SIZE / (SIZE as f32);
 fn main() {
     let mut canvas = Canvas::new(SIZE, SIZE, Color::black());
     let ray_origin = Point::new(0., 0., -5.);
-    let s = WorldObject::Sphere(Sphere::new());
+    let s = Sphere::new();
 
     for y in 0..SIZE {
         let wall_y: f32 = WALL_SIZE / 2.0 - (y as f32) * PIXEL_SIZE;
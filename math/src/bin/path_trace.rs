@@ -0,0 +1,85 @@
+use math::camera::Camera;
+use math::color::Color;
+use math::image::Image;
+use math::light::PointLight;
+use math::material::Material;
+use math::matrix::Matrix;
+use math::path_tracer::PathTracer;
+use math::plane::Plane;
+use math::point::point;
+use math::sphere::Sphere;
+use math::transforms::{rotation_x, rotation_z, scaling, translation};
+use math::vector::vector;
+use math::view::view_transform;
+use math::world::World;
+use std::f32::consts::PI;
+
+const SIZE: usize = 200;
+const SAMPLES_PER_PIXEL: usize = 64;
+
+fn diffuse(color: Color) -> Material {
+    let mut m = Material::new();
+    m.color = color;
+    m
+}
+
+fn wall(transform: Matrix<4, 4>, color: Color) -> Box<Plane> {
+    let mut p = Plane::new();
+    p.set_transform(transform).unwrap();
+    p.set_material(diffuse(color));
+    Box::new(p)
+}
+
+/// A box with a red and a green wall, to show indirect light bleeding their
+/// colors onto the white surfaces.
+fn main() {
+    let white = Color::new(0.8, 0.8, 0.8);
+    let mut w = World::new();
+    w.add_object(wall(translation(0.0, 0.0, 0.0), white));
+    w.add_object(wall(translation(0.0, 5.0, 0.0), white));
+    w.add_object(wall(
+        &translation(0.0, 0.0, 5.0) * &rotation_x(PI / 2.0),
+        white,
+    ));
+    w.add_object(wall(
+        &translation(-2.5, 0.0, 0.0) * &rotation_z(PI / 2.0),
+        Color::new(0.8, 0.1, 0.1),
+    ));
+    w.add_object(wall(
+        &translation(2.5, 0.0, 0.0) * &rotation_z(PI / 2.0),
+        Color::new(0.1, 0.8, 0.1),
+    ));
+    w.add_object(Box::new({
+        let mut s = Sphere::new();
+        s.set_transform(translation(-1.0, 1.0, 3.0)).unwrap();
+        s.set_material(diffuse(white));
+        s
+    }));
+    w.add_object(Box::new({
+        let mut s = Sphere::new();
+        s.set_transform(&translation(1.2, 0.8, 1.5) * &scaling(0.8, 0.8, 0.8))
+            .unwrap();
+        s.set_material({
+            let mut m = Material::new();
+            m.reflective = 1.0;
+            m
+        });
+        s
+    }));
+    w.lights.push(Box::new(PointLight::new(
+        point(0.0, 4.5, 2.5),
+        Color::new(3.0, 3.0, 3.0),
+    )));
+
+    let mut camera = Camera::new(SIZE, SIZE, PI / 3.0);
+    camera
+        .set_transform(view_transform(
+            point(0.0, 2.5, -4.0),
+            point(0.0, 2.5, 0.0),
+            vector(0, 1, 0),
+        ))
+        .unwrap();
+
+    let tracer = PathTracer::new(SAMPLES_PER_PIXEL);
+    Image::from_canvas(tracer.render(&camera, &w)).write_png("path_trace.out.png");
+}
@@ -1,58 +1,44 @@
-use math::canvas::Canvas;
+use math::camera::Camera;
 use math::color::Color;
 use math::image::Image;
-use math::intersect::hit;
 use math::light::PointLight;
-use math::lighting;
 use math::material::Material;
-use math::point::{point, Point};
-use math::ray::Ray;
+use math::point::point;
 use math::sphere::Sphere;
-use math::world::{Intersect, WorldObject};
+use math::vector::vector;
+use math::view::view_transform;
+use math::world::World;
 
 const SIZE: usize = 500;
 const WALL_Z: f32 = 10.0;
 const WALL_SIZE: f32 = 7.0;
-const PIXEL_SIZE: f32 = WALL_SIZE / (SIZE as f32);
+const EYE_Z: f32 = -5.0;
 
 fn main() {
-    let mut canvas = Canvas::new(SIZE, SIZE, Color::white());
-    let ray_origin = Point::new(0., 0., -5.);
+    let mut w = World::new();
+    w.add_object(Box::new({
+        let mut s = Sphere::new();
+        s.set_material({
+            let mut m = Material::new();
+            m.color = Color::new(1.0, 0.2, 1.0);
+            m
+        });
+        s
+    }));
+    w.lights.push(Box::new(PointLight::new(
+        point(-10., 10., -10.),
+        Color::new(1., 1., 1.),
+    )));
 
-    let mut s = Sphere::new();
-    s.set_material({
-        let mut m = Material::new();
-        m.color = Color::new(1.0, 0.2, 1.0);
-        m
-    });
-    let s = WorldObject::Sphere(s);
+    let field_of_view = 2.0 * (WALL_SIZE / 2.0 / (WALL_Z - EYE_Z)).atan();
+    let mut camera = Camera::new(SIZE, SIZE, field_of_view);
+    camera
+        .set_transform(view_transform(
+            point(0.0, 0.0, EYE_Z),
+            point(0, 0, 0),
+            vector(0, 1, 0),
+        ))
+        .unwrap();
 
-    let light = PointLight {
-        position: point(-10., 10., -10.),
-        intensity: Color::new(1., 1., 1.),
-    };
-
-    for y in 0..SIZE {
-        let wall_y: f32 = WALL_SIZE / 2.0 - (y as f32) * PIXEL_SIZE;
-        for x in 0..SIZE {
-            let wall_x: f32 = (x as f32) * PIXEL_SIZE - WALL_SIZE / 2.0;
-            let wall = Point::new(wall_x, wall_y, WALL_Z);
-
-            let r = Ray::new(ray_origin, (wall - ray_origin).normalized());
-            let xs = s.intersect(&r);
-            if let Some(h) = hit(&xs) {
-                let point = r.position(h.t);
-                let eye = -r.direction;
-                let color = lighting::phong(
-                    h.object.material_at(point),
-                    &light,
-                    point,
-                    eye,
-                    h.object.normal_at(point),
-                );
-                canvas.set(x, y, color);
-            }
-        }
-    }
-    Image::from_canvas(canvas).write_png("sphere.out.png");
+    Image::from_canvas(camera.render(&w)).write_png("sphere.out.png");
 }
@@ -0,0 +1,263 @@
+use crate::matrix::Matrix;
+use crate::point::{point, Point};
+use crate::ray::Ray;
+use crate::transforms::Transform;
+
+/// An axis-aligned bounding box.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    pub fn new(min: Point, max: Point) -> Bounds {
+        Bounds { min, max }
+    }
+
+    /// A box containing nothing, it is the identity for `merge`.
+    pub fn empty() -> Bounds {
+        Bounds::new(
+            point(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            point(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        )
+    }
+
+    pub fn infinite() -> Bounds {
+        Bounds::new(
+            point(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            point(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn is_finite(&self) -> bool {
+        [self.min, self.max]
+            .iter()
+            .all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite())
+    }
+
+    pub fn add_point(&mut self, p: Point) {
+        self.min = point(
+            self.min.x.min(p.x),
+            self.min.y.min(p.y),
+            self.min.z.min(p.z),
+        );
+        self.max = point(
+            self.max.x.max(p.x),
+            self.max.y.max(p.y),
+            self.max.z.max(p.z),
+        );
+    }
+
+    pub fn merge(&self, other: &Bounds) -> Bounds {
+        let mut b = *self;
+        if !other.is_empty() {
+            b.add_point(other.min);
+            b.add_point(other.max);
+        }
+        b
+    }
+
+    pub fn contains_point(&self, p: Point) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    pub fn contains_box(&self, other: &Bounds) -> bool {
+        self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    pub fn centroid(&self) -> Point {
+        point(
+            (self.min.x + self.max.x) / 2.0,
+            (self.min.y + self.max.y) / 2.0,
+            (self.min.z + self.max.z) / 2.0,
+        )
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Returns the bounds of this box after applying `t` to it.
+    pub fn transformed(&self, t: &Matrix<4, 4>) -> Bounds {
+        if self.is_empty() {
+            return *self;
+        }
+        if !self.is_finite() {
+            return Bounds::infinite();
+        }
+        let mut b = Bounds::empty();
+        for x in [self.min.x, self.max.x] {
+            for y in [self.min.y, self.max.y] {
+                for z in [self.min.z, self.max.z] {
+                    b.add_point(point(x, y, z).apply(t));
+                }
+            }
+        }
+        b
+    }
+
+    /// Returns the range of `t` in which the ray is inside the box.
+    pub fn intersect(&self, r: &Ray) -> Option<(f32, f32)> {
+        let (xtmin, xtmax) = check_axis(r.origin.x, r.direction.x, self.min.x, self.max.x);
+        let (ytmin, ytmax) = check_axis(r.origin.y, r.direction.y, self.min.y, self.max.y);
+        let (ztmin, ztmax) = check_axis(r.origin.z, r.direction.z, self.min.z, self.max.z);
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+        if tmin > tmax {
+            None
+        } else {
+            Some((tmin, tmax))
+        }
+    }
+}
+
+fn check_axis(origin: f32, direction: f32, min: f32, max: f32) -> (f32, f32) {
+    if direction == 0.0 {
+        return if min <= origin && origin <= max {
+            (f32::NEG_INFINITY, f32::INFINITY)
+        } else {
+            (f32::INFINITY, f32::NEG_INFINITY)
+        };
+    }
+    let tmin = (min - origin) / direction;
+    let tmax = (max - origin) / direction;
+    if tmin > tmax {
+        (tmax, tmin)
+    } else {
+        (tmin, tmax)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::transforms::{rotation_x, rotation_y};
+    use crate::vector::vector;
+    use std::f32::consts::{PI, SQRT_2};
+
+    #[test]
+    fn test_create_empty_bounds() {
+        let b = Bounds::empty();
+        assert!(b.is_empty());
+        assert_eq!(b.surface_area(), 0.0);
+    }
+
+    #[test]
+    fn test_add_points_to_bounds() {
+        let mut b = Bounds::empty();
+        b.add_point(point(-5, 2, 0));
+        b.add_point(point(7, 0, -3));
+        assert_eq!(b, Bounds::new(point(-5, 0, -3), point(7, 2, 0)));
+    }
+
+    #[test]
+    fn test_merge_bounds() {
+        let b1 = Bounds::new(point(-5, -2, 0), point(7, 4, 4));
+        let b2 = Bounds::new(point(8, -7, -2), point(14, 2, 8));
+        assert_eq!(
+            b1.merge(&b2),
+            Bounds::new(point(-5, -7, -2), point(14, 4, 8))
+        );
+        assert_eq!(b1.merge(&Bounds::empty()), b1);
+    }
+
+    #[test]
+    fn test_bounds_contains_point() {
+        let b = Bounds::new(point(5, -2, 0), point(11, 4, 7));
+        let cases = [
+            (point(5, -2, 0), true),
+            (point(11, 4, 7), true),
+            (point(8, 1, 3), true),
+            (point(3, 0, 3), false),
+            (point(8, -4, 3), false),
+            (point(8, 1, -1), false),
+            (point(13, 1, 3), false),
+            (point(8, 5, 3), false),
+            (point(8, 1, 8), false),
+        ];
+        for (p, result) in cases {
+            assert_eq!(b.contains_point(p), result);
+        }
+    }
+
+    #[test]
+    fn test_bounds_contains_box() {
+        let b = Bounds::new(point(5, -2, 0), point(11, 4, 7));
+        let cases = [
+            (point(5, -2, 0), point(11, 4, 7), true),
+            (point(6, -1, 1), point(10, 3, 6), true),
+            (point(4, -3, -1), point(10, 3, 6), false),
+            (point(6, -1, 1), point(12, 5, 8), false),
+        ];
+        for (min, max, result) in cases {
+            assert_eq!(b.contains_box(&Bounds::new(min, max)), result);
+        }
+    }
+
+    #[test]
+    fn test_transform_bounds() {
+        let b = Bounds::new(point(-1, -1, -1), point(1, 1, 1));
+        let t = &rotation_x(PI / 4.0) * &rotation_y(PI / 4.0);
+        let b = b.transformed(&t);
+        assert_near!(b.min, point(-SQRT_2, -1.70711, -1.70711));
+        assert_near!(b.max, point(SQRT_2, 1.70711, 1.70711));
+    }
+
+    #[test]
+    fn test_transform_infinite_bounds() {
+        let b = Bounds::new(
+            point(f32::NEG_INFINITY, 0.0, f32::NEG_INFINITY),
+            point(f32::INFINITY, 0.0, f32::INFINITY),
+        );
+        assert_eq!(b.transformed(&rotation_x(1.0)), Bounds::infinite());
+    }
+
+    #[test]
+    fn test_surface_area() {
+        let b = Bounds::new(point(0, 0, 0), point(1, 2, 3));
+        assert_eq!(b.surface_area(), 22.0);
+    }
+
+    #[test]
+    fn test_intersect_ray_with_bounds() {
+        let b = Bounds::new(point(5, -2, 0), point(11, 4, 7));
+        let cases = [
+            (point(15, 1, 2), vector(-1, 0, 0), true),
+            (point(-5, -1, 4), vector(1, 0, 0), true),
+            (point(7, 6, 5), vector(0, -1, 0), true),
+            (point(9, -5, 6), vector(0, 1, 0), true),
+            (point(8, 2, 12), vector(0, 0, -1), true),
+            (point(6, 0, -5), vector(0, 0, 1), true),
+            (point(8.0, 1.0, 3.5), vector(0, 0, 1), true),
+            (point(9, -1, -8), vector(2, 4, 6), false),
+            (point(8, 3, -4), vector(6, 2, 4), false),
+            (point(9, -1, -2), vector(4, 6, 2), false),
+            (point(4, 0, 9), vector(0, 0, -1), false),
+            (point(8, 6, -1), vector(0, -1, 0), false),
+            (point(12, 5, 4), vector(-1, 0, 0), false),
+        ];
+        for (origin, direction, result) in cases {
+            let r = Ray::new(origin, direction.normalized());
+            assert_eq!(b.intersect(&r).is_some(), result);
+        }
+    }
+
+    #[test]
+    fn test_intersect_ray_with_bounds_range() {
+        let b = Bounds::new(point(-1, -1, -1), point(1, 1, 1));
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        assert_eq!(b.intersect(&r), Some((4.0, 6.0)));
+    }
+}
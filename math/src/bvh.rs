@@ -0,0 +1,348 @@
+use crate::bounds::Bounds;
+use crate::intersect::Intersection;
+use crate::point::Point;
+use crate::ray::Ray;
+use crate::shape::Shape;
+
+const BUCKETS: usize = 12;
+
+/// Relative cost of visiting a node compared to intersecting a shape.
+const TRAVERSAL_COST: f32 = 0.125;
+
+#[derive(Debug)]
+enum Node {
+    Leaf { first: usize, count: usize },
+    Inner { right: usize },
+}
+
+/// A bounding volume hierarchy over a list of shapes, built with the surface
+/// area heuristic.
+///
+/// The hierarchy only stores indices, so the same list of shapes must be
+/// passed to `intersect` and `hit`. Rays are given in the space the shapes
+/// are placed in, i.e. world space for a `World` and object space of the
+/// group for a `Group`.
+#[derive(Debug)]
+pub struct Bvh {
+    nodes: Vec<(Bounds, Node)>,
+    indices: Vec<usize>,
+    /// Shapes without finite bounds, like planes, are tested for every ray.
+    unbounded: Vec<usize>,
+}
+
+struct Item {
+    index: usize,
+    bounds: Bounds,
+    centroid: Point,
+}
+
+fn axis(p: Point, axis: usize) -> f32 {
+    match axis {
+        0 => p.x,
+        1 => p.y,
+        _ => p.z,
+    }
+}
+
+impl Bvh {
+    pub fn build(shapes: &[Box<dyn Shape>]) -> Bvh {
+        let mut items = vec![];
+        let mut unbounded = vec![];
+        for (index, shape) in shapes.iter().enumerate() {
+            let bounds = shape.parent_space_bounds();
+            if bounds.is_empty() {
+                continue;
+            }
+            if bounds.is_finite() {
+                let centroid = bounds.centroid();
+                items.push(Item {
+                    index,
+                    bounds,
+                    centroid,
+                });
+            } else {
+                unbounded.push(index);
+            }
+        }
+        let mut bvh = Bvh {
+            nodes: vec![],
+            indices: vec![],
+            unbounded,
+        };
+        if !items.is_empty() {
+            bvh.build_node(&mut items, 0);
+        }
+        bvh.indices = items.iter().map(|item| item.index).collect();
+        bvh
+    }
+
+    /// The number of nodes in the hierarchy, leaves included.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn bounds(&self) -> Bounds {
+        let mut bounds = match self.nodes.first() {
+            Some((b, _)) => *b,
+            None => Bounds::empty(),
+        };
+        if !self.unbounded.is_empty() {
+            bounds = Bounds::infinite();
+        }
+        bounds
+    }
+
+    fn build_node(&mut self, items: &mut [Item], first: usize) {
+        let bounds = items
+            .iter()
+            .fold(Bounds::empty(), |b, item| b.merge(&item.bounds));
+        let node = self.nodes.len();
+        self.nodes.push((
+            bounds,
+            Node::Leaf {
+                first,
+                count: items.len(),
+            },
+        ));
+        if items.len() <= 1 {
+            return;
+        }
+
+        let mut centroids = Bounds::empty();
+        for item in items.iter() {
+            centroids.add_point(item.centroid);
+        }
+        let extent = centroids.max - centroids.min;
+        let split_axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let lo = axis(centroids.min, split_axis);
+        let hi = axis(centroids.max, split_axis);
+        if hi <= lo {
+            return;
+        }
+        let bucket_of = |item: &Item| {
+            let b = (BUCKETS as f32 * (axis(item.centroid, split_axis) - lo) / (hi - lo)) as usize;
+            b.min(BUCKETS - 1)
+        };
+
+        let mut counts = [0; BUCKETS];
+        let mut boxes = [Bounds::empty(); BUCKETS];
+        for item in items.iter() {
+            let b = bucket_of(item);
+            counts[b] += 1;
+            boxes[b] = boxes[b].merge(&item.bounds);
+        }
+
+        let mut best = None;
+        for split in 1..BUCKETS {
+            let (mut left, mut right) = (Bounds::empty(), Bounds::empty());
+            let (mut nleft, mut nright) = (0, 0);
+            for b in 0..BUCKETS {
+                if b < split {
+                    left = left.merge(&boxes[b]);
+                    nleft += counts[b];
+                } else {
+                    right = right.merge(&boxes[b]);
+                    nright += counts[b];
+                }
+            }
+            if nleft == 0 || nright == 0 {
+                continue;
+            }
+            let cost = TRAVERSAL_COST
+                + (left.surface_area() * nleft as f32 + right.surface_area() * nright as f32)
+                    / bounds.surface_area();
+            if best.is_none_or(|(_, best_cost)| cost < best_cost) {
+                best = Some((split, cost));
+            }
+        }
+        let split = match best {
+            Some((split, cost)) if cost < items.len() as f32 => split,
+            _ => return,
+        };
+
+        let mut mid = 0;
+        for i in 0..items.len() {
+            if bucket_of(&items[i]) < split {
+                items.swap(i, mid);
+                mid += 1;
+            }
+        }
+        let (left, right) = items.split_at_mut(mid);
+        self.build_node(left, first);
+        let right_node = self.nodes.len();
+        self.build_node(right, first + mid);
+        self.nodes[node].1 = Node::Inner { right: right_node };
+    }
+
+    /// Returns all intersections of the ray with the shapes, unsorted.
+    pub fn intersect<'a>(&self, shapes: &'a [Box<dyn Shape>], r: &Ray) -> Vec<Intersection<'a>> {
+        let mut xs = vec![];
+        for &i in &self.unbounded {
+            xs.extend(shapes[i].intersect(r));
+        }
+        let mut stack = vec![];
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(node) = stack.pop() {
+            let (bounds, kind) = &self.nodes[node];
+            if bounds.intersect(r).is_none() {
+                continue;
+            }
+            match *kind {
+                Node::Leaf { first, count } => {
+                    for &i in &self.indices[first..first + count] {
+                        xs.extend(shapes[i].intersect(r));
+                    }
+                }
+                Node::Inner { right } => {
+                    stack.push(right);
+                    stack.push(node + 1);
+                }
+            }
+        }
+        xs
+    }
+
+    /// Returns the closest intersection with positive `t`, skipping the
+    /// nodes that are farther away than the best hit found so far.
+    pub fn hit<'a>(&self, shapes: &'a [Box<dyn Shape>], r: &Ray) -> Option<Intersection<'a>> {
+        let mut best: Option<Intersection<'a>> = None;
+        let consider = |x: Intersection<'a>, best: &mut Option<Intersection<'a>>| {
+            if x.t > 0.0 && best.is_none_or(|b| x.t < b.t) {
+                *best = Some(x);
+            }
+        };
+        for &i in &self.unbounded {
+            for x in shapes[i].intersect(r) {
+                consider(x, &mut best);
+            }
+        }
+        let mut stack = vec![];
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(node) = stack.pop() {
+            let (bounds, kind) = &self.nodes[node];
+            match bounds.intersect(r) {
+                Some((tmin, tmax)) if tmax > 0.0 && best.is_none_or(|b| tmin < b.t) => {}
+                _ => continue,
+            }
+            match *kind {
+                Node::Leaf { first, count } => {
+                    for &i in &self.indices[first..first + count] {
+                        for x in shapes[i].intersect(r) {
+                            consider(x, &mut best);
+                        }
+                    }
+                }
+                Node::Inner { right } => {
+                    stack.push(right);
+                    stack.push(node + 1);
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intersect::hit;
+    use crate::plane::Plane;
+    use crate::point::point;
+    use crate::sphere::Sphere;
+    use crate::transforms::translation;
+    use crate::vector::vector;
+
+    fn sphere_at(x: f32, y: f32, z: f32) -> Box<dyn Shape> {
+        let mut s = Sphere::new();
+        s.set_transform(translation(x, y, z)).unwrap();
+        Box::new(s)
+    }
+
+    fn sphere_row(n: usize) -> Vec<Box<dyn Shape>> {
+        (0..n)
+            .map(|i| sphere_at(3.0 * i as f32, 0.0, 0.0))
+            .collect()
+    }
+
+    #[test]
+    fn test_build_empty_bvh() {
+        let bvh = Bvh::build(&[]);
+        assert_eq!(bvh.node_count(), 0);
+        assert!(bvh.bounds().is_empty());
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        assert!(bvh.intersect(&[], &r).is_empty());
+        assert!(bvh.hit(&[], &r).is_none());
+    }
+
+    #[test]
+    fn test_build_splits_distant_shapes() {
+        let shapes = sphere_row(16);
+        let bvh = Bvh::build(&shapes);
+        assert!(bvh.node_count() > 1);
+        assert_eq!(
+            bvh.bounds(),
+            Bounds::new(point(-1, -1, -1), point(46, 1, 1))
+        );
+    }
+
+    #[test]
+    fn test_intersect_matches_brute_force() {
+        let shapes = sphere_row(16);
+        let bvh = Bvh::build(&shapes);
+        for i in 0..16 {
+            let r = Ray::new(point(3.0 * i as f32, 0.5, -5.0), vector(0, 0, 1));
+            let xs = bvh.intersect(&shapes, &r);
+            assert_eq!(xs.len(), 2);
+            assert_eq!(xs[0].object, shapes[i].as_ref());
+        }
+        let r = Ray::new(point(1.5, 0.0, -5.0), vector(0, 0, 1));
+        assert!(bvh.intersect(&shapes, &r).is_empty());
+    }
+
+    #[test]
+    fn test_hit_finds_closest_shape() {
+        let shapes = sphere_row(16);
+        let bvh = Bvh::build(&shapes);
+        let r = Ray::new(point(100, 0, 0), vector(-1, 0, 0));
+        let h = bvh.hit(&shapes, &r).unwrap();
+        assert_eq!(h.object, shapes[15].as_ref());
+        assert_eq!(h.t, 54.0);
+        assert_eq!(Some(h), hit(&bvh.intersect(&shapes, &r)));
+    }
+
+    #[test]
+    fn test_hit_from_inside_shape() {
+        let shapes = sphere_row(4);
+        let bvh = Bvh::build(&shapes);
+        let r = Ray::new(point(3, 0, 0), vector(1, 0, 0));
+        let h = bvh.hit(&shapes, &r).unwrap();
+        assert_eq!(h.object, shapes[1].as_ref());
+        assert_eq!(h.t, 1.0);
+    }
+
+    #[test]
+    fn test_unbounded_shapes_are_always_tested() {
+        let mut shapes = sphere_row(4);
+        shapes.push(Box::new({
+            let mut p = Plane::new();
+            p.set_transform(translation(0.0, -10.0, 0.0)).unwrap();
+            p
+        }));
+        let bvh = Bvh::build(&shapes);
+        assert_eq!(bvh.bounds(), Bounds::infinite());
+        let r = Ray::new(point(100, 0, 0), vector(0, -1, 0));
+        let h = bvh.hit(&shapes, &r).unwrap();
+        assert_eq!(h.object, shapes[4].as_ref());
+        assert_eq!(h.t, 10.0);
+    }
+}
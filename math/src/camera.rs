@@ -1,14 +1,18 @@
+use crate::canvas::Canvas;
+use crate::color::Color;
 use crate::matrix::Matrix;
 use crate::point::point;
+use crate::random::Rng;
 use crate::ray::Ray;
+use crate::transformation::Transformation;
 use crate::transforms::Transform;
+use crate::world::{World, MAX_DEPTH};
 
 pub struct Camera {
     hsize: usize,
     vsize: usize,
     field_of_view: f32,
-    transform: Matrix<4, 4>,
-    inv_transform: Matrix<4, 4>,
+    transform: Transformation,
     half_width: f32,
     half_height: f32,
     pixel_size: f32,
@@ -28,28 +32,62 @@ impl Camera {
             hsize,
             vsize,
             field_of_view,
-            transform: Matrix::identity(),
-            inv_transform: Matrix::identity(),
+            transform: Transformation::identity(),
             half_width,
             half_height,
             pixel_size,
         }
     }
 
+    pub fn hsize(&self) -> usize {
+        self.hsize
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
+
+    pub fn field_of_view(&self) -> f32 {
+        self.field_of_view
+    }
+
     pub fn ray_for_pixel(&self, x: usize, y: usize) -> Ray {
-        let x = (x as f32 + 0.5) * self.pixel_size;
-        let y = (y as f32 + 0.5) * self.pixel_size;
+        self.ray_for_subpixel(x, y, 0.5, 0.5)
+    }
+
+    /// Like `ray_for_pixel`, but through the point at offset `(dx, dy)`
+    /// inside the pixel, both in `[0, 1)`.
+    pub fn ray_for_subpixel(&self, x: usize, y: usize, dx: f32, dy: f32) -> Ray {
+        let x = (x as f32 + dx) * self.pixel_size;
+        let y = (y as f32 + dy) * self.pixel_size;
         let x = self.half_width - x;
         let y = self.half_height - y;
-        let pixel = point(x, y, -1.0).apply(&self.inv_transform);
-        let origin = point(0, 0, 0).apply(&self.inv_transform);
+        let pixel = point(x, y, -1.0).apply(self.transform.inverse());
+        let origin = point(0, 0, 0).apply(self.transform.inverse());
         let direction = (pixel - origin).normalized();
         Ray { origin, direction }
     }
 
-    pub fn set_transform(&mut self, transform: Matrix<4, 4>) {
-        self.transform = transform;
-        self.inv_transform = self.transform.inverse().unwrap();
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+
+    pub fn render_pixel(&self, w: &World, x: usize, y: usize, rng: &mut Rng) -> Color {
+        w.color_at(&self.ray_for_pixel(x, y), MAX_DEPTH, rng)
+    }
+
+    /// Renders the world, drawing the samples of soft lights from a fixed
+    /// seed so that rendering twice gives the same image.
+    pub fn render(&self, w: &World) -> Canvas {
+        let mut rng = Rng::new(0);
+        let mut image = Canvas::new(self.hsize, self.vsize, Color::black());
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                image.set(x, y, self.render_pixel(w, x, y, &mut rng));
+            }
+        }
+        image
     }
 }
 
@@ -60,15 +98,17 @@ mod tests {
     use crate::test_utils::*;
     use crate::transforms;
     use crate::vector::vector;
+    use crate::view::view_transform;
+    use crate::world::default_world;
     use std::f32::consts::{FRAC_1_SQRT_2, PI};
 
     #[test]
     fn test_create_camera() {
         let c = Camera::new(160, 120, PI / 2.0);
-        assert_eq!(c.hsize, 160);
-        assert_eq!(c.vsize, 120);
-        assert_eq!(c.field_of_view, PI / 2.0);
-        assert_eq!(c.transform, Matrix::identity());
+        assert_eq!(c.hsize(), 160);
+        assert_eq!(c.vsize(), 120);
+        assert_eq!(c.field_of_view(), PI / 2.0);
+        assert_eq!(c.transform.matrix(), &Matrix::identity());
     }
 
     #[test]
@@ -91,6 +131,17 @@ mod tests {
         assert_near!(r.direction, vector(0, 0, -1));
     }
 
+    #[test]
+    fn test_ray_through_subpixel() {
+        let c = Camera::new(201, 101, PI / 2.0);
+        assert_eq!(
+            c.ray_for_subpixel(100, 50, 0.5, 0.5),
+            c.ray_for_pixel(100, 50)
+        );
+        let r = c.ray_for_subpixel(0, 0, 0.0, 0.0);
+        assert_near!(r.direction, vector(1.0, 0.50248, -1.0).normalized(), 1e-4);
+    }
+
     #[test]
     fn test_ray_through_corner() {
         let c = Camera::new(201, 101, PI / 2.0);
@@ -104,9 +155,53 @@ mod tests {
         let mut c = Camera::new(201, 101, PI / 2.0);
         c.set_transform(
             &transforms::rotation_y(PI / 4.0) * &transforms::translation(0.0, -2.0, 5.0),
-        );
+        )
+        .unwrap();
         let r = c.ray_for_pixel(100, 50);
         assert_near!(r.origin, point(0, 2, -5));
         assert_near!(r.direction, vector(FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2));
     }
+
+    #[test]
+    fn test_singular_camera_transform() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        assert_eq!(
+            c.set_transform(view_transform(
+                point(0, 0, 0),
+                point(0, 0, -1),
+                vector(0, 0, 1)
+            )),
+            Err("Transform matrix is not invertible")
+        );
+    }
+
+    #[test]
+    fn test_render_world_with_camera() {
+        let w = default_world();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            point(0, 0, -5),
+            point(0, 0, 0),
+            vector(0, 1, 0),
+        ))
+        .unwrap();
+        let image = c.render(&w);
+        assert_near!(image.get(5, 5), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn test_render_single_pixel() {
+        let w = default_world();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            point(0, 0, -5),
+            point(0, 0, 0),
+            vector(0, 1, 0),
+        ))
+        .unwrap();
+        assert_near!(
+            c.render_pixel(&w, 5, 5, &mut Rng::new(0)),
+            Color::new(0.38066, 0.47583, 0.2855)
+        );
+    }
 }
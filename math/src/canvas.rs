@@ -1,5 +1,6 @@
 use crate::color::Color;
 
+#[derive(Debug, PartialEq, Clone)]
 pub struct Canvas {
     pub width: usize,
     pub height: usize,
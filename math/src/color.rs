@@ -17,6 +17,11 @@ impl Color {
     pub fn black() -> Color {
         Color::new(0.0, 0.0, 0.0)
     }
+
+    /// Perceived brightness, with the Rec. 709 weights.
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue
+    }
 }
 
 impl std::ops::Add for Color {
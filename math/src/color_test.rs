@@ -38,3 +38,10 @@ fn test_multiplying_colors() {
     let c2 = Color::new(0.9, 1.0, 0.1);
     assert_near!(hadamard_product(c1, c2), Color::new(0.9, 0.2, 0.04));
 }
+
+#[test]
+fn test_luminance() {
+    assert_near!(Color::white().luminance(), 1.0);
+    assert_eq!(Color::black().luminance(), 0.0);
+    assert!(Color::new(0.0, 1.0, 0.0).luminance() > Color::new(1.0, 0.0, 1.0).luminance());
+}
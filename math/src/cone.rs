@@ -0,0 +1,222 @@
+use crate::approx::EPSILON;
+use crate::bounds::Bounds;
+use crate::cylinder::check_cap;
+use crate::intersect::Intersection;
+use crate::material::Material;
+use crate::matrix::Matrix;
+use crate::point::{point, Point};
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::transformation::Transformation;
+use crate::vector::{vector, Vector};
+
+/// A double-napped cone `x^2 + z^2 = y^2` around the y axis, truncated to
+/// `minimum < y < maximum` and optionally capped at both ends.
+#[derive(Debug, PartialEq)]
+pub struct Cone {
+    transform: Transformation,
+    material: Material,
+    pub minimum: f32,
+    pub maximum: f32,
+    pub closed: bool,
+}
+
+impl Cone {
+    pub fn new() -> Cone {
+        Cone {
+            transform: Transformation::identity(),
+            material: Material::new(),
+            minimum: f32::NEG_INFINITY,
+            maximum: f32::INFINITY,
+            closed: false,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn intersect_caps<'a>(&'a self, r: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        if !self.closed || r.direction.y.abs() < EPSILON {
+            return;
+        }
+        for y in [self.minimum, self.maximum] {
+            let t = (y - r.origin.y) / r.direction.y;
+            if check_cap(r, t, y.abs()) {
+                xs.push(Intersection::new(self, t));
+            }
+        }
+    }
+
+    fn push_if_in_bounds<'a>(&'a self, r: &Ray, t: f32, xs: &mut Vec<Intersection<'a>>) {
+        let y = r.origin.y + t * r.direction.y;
+        if self.minimum < y && y < self.maximum {
+            xs.push(Intersection::new(self, t));
+        }
+    }
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Cone::new()
+    }
+}
+
+impl Shape for Cone {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Transformation) {
+        self.transform.set_parent(parent);
+    }
+
+    fn local_intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
+        let mut xs = vec![];
+
+        let (o, d) = (r.origin, r.direction);
+        let a = d.x * d.x - d.y * d.y + d.z * d.z;
+        let b = 2.0 * (o.x * d.x - o.y * d.y + o.z * d.z);
+        let c = o.x * o.x - o.y * o.y + o.z * o.z;
+
+        if a.abs() < EPSILON {
+            if b.abs() >= EPSILON {
+                self.push_if_in_bounds(r, -c / (2.0 * b), &mut xs);
+            }
+        } else {
+            let disc = b * b - 4.0 * a * c;
+            if disc < -EPSILON {
+                return xs;
+            }
+            // Rays grazing the cone can come out slightly negative in f32.
+            let disc = disc.max(0.0);
+            let t0 = (-b - disc.sqrt()) / (2.0 * a);
+            let t1 = (-b + disc.sqrt()) / (2.0 * a);
+            self.push_if_in_bounds(r, t0.min(t1), &mut xs);
+            self.push_if_in_bounds(r, t0.max(t1), &mut xs);
+        }
+
+        self.intersect_caps(r, &mut xs);
+        xs
+    }
+
+    fn bounds(&self) -> Bounds {
+        let r = self.minimum.abs().max(self.maximum.abs());
+        Bounds::new(point(-r, self.minimum, -r), point(r, self.maximum, r))
+    }
+
+    fn local_normal_at(&self, p: Point) -> Vector {
+        let dist = p.x * p.x + p.z * p.z;
+        if dist < p.y * p.y && p.y >= self.maximum - EPSILON {
+            vector(0, 1, 0)
+        } else if dist < p.y * p.y && p.y <= self.minimum + EPSILON {
+            vector(0, -1, 0)
+        } else {
+            let y = dist.sqrt();
+            vector(p.x, if p.y > 0.0 { -y } else { y }, p.z)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::point;
+    use crate::test_utils::*;
+    use std::f32::consts::SQRT_2;
+
+    #[test]
+    fn test_ray_strikes_cone() {
+        let shape = Cone::new();
+        let cases = [
+            (point(0, 0, -5), vector(0, 0, 1), 5.0, 5.0),
+            (point(0, 0, -5), vector(1, 1, 1), 8.66025, 8.66025),
+            (point(1, 1, -5), vector(-0.5, -1.0, 1.0), 4.55006, 49.44994),
+        ];
+        for (origin, direction, t0, t1) in cases {
+            let r = Ray::new(origin, direction.normalized());
+            let xs = ts(shape.local_intersect(&r));
+            assert_eq!(xs.len(), 2);
+            assert_near!(xs[0], t0, 1e-3);
+            assert_near!(xs[1], t1, 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_ray_parallel_to_one_half_of_cone() {
+        let shape = Cone::new();
+        let r = Ray::new(point(0, 0, -1), vector(0, 1, 1).normalized());
+        let xs = ts(shape.local_intersect(&r));
+        assert_eq!(xs.len(), 1);
+        assert_near!(xs[0], 0.35355, 1e-4);
+    }
+
+    #[test]
+    fn test_intersect_caps_of_closed_cone() {
+        let mut shape = Cone::new();
+        shape.minimum = -0.5;
+        shape.maximum = 0.5;
+        shape.closed = true;
+        let cases = [
+            (point(0, 0, -5), vector(0, 1, 0), 0),
+            (point(0.0, 0.0, -0.25), vector(0, 1, 1), 2),
+            (point(0.0, 0.0, -0.25), vector(0, 1, 0), 4),
+        ];
+        for (origin, direction, count) in cases {
+            let r = Ray::new(origin, direction.normalized());
+            assert_eq!(shape.local_intersect(&r).len(), count);
+        }
+    }
+
+    #[test]
+    fn test_normal_on_cone() {
+        let shape = Cone::new();
+        let cases = [
+            (point(0, 0, 0), vector(0, 0, 0)),
+            (point(1, 1, 1), vector(1.0, -SQRT_2, 1.0)),
+            (point(-1, -1, 0), vector(-1, 1, 0)),
+        ];
+        for (p, n) in cases {
+            assert_near!(shape.local_normal_at(p), n);
+        }
+    }
+
+    #[test]
+    fn test_normal_on_cone_end_caps() {
+        let mut shape = Cone::new();
+        shape.minimum = -1.0;
+        shape.maximum = 2.0;
+        shape.closed = true;
+        assert_eq!(shape.local_normal_at(point(0.5, 2.0, 0.0)), vector(0, 1, 0));
+        assert_eq!(
+            shape.local_normal_at(point(0.0, -1.0, 0.5)),
+            vector(0, -1, 0)
+        );
+    }
+
+    #[test]
+    fn test_assigned_material() {
+        let mut shape = Cone::new();
+        let mut m = Material::new();
+        m.ambient = 1.0;
+        shape.set_material(m.clone());
+        assert_eq!(shape.material(), m);
+    }
+
+    #[test]
+    fn test_truncated_cone_bounds() {
+        let mut c = Cone::new();
+        c.minimum = -5.0;
+        c.maximum = 3.0;
+        assert_eq!(c.bounds(), Bounds::new(point(-5, -5, -5), point(5, 3, 5)));
+    }
+}
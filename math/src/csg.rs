@@ -0,0 +1,261 @@
+use crate::bounds::Bounds;
+use crate::intersect::Intersection;
+use crate::material::Material;
+use crate::matrix::Matrix;
+use crate::point::Point;
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::transformation::Transformation;
+use crate::vector::Vector;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOperation {
+    /// Decides whether a hit on the left (`lhit`) or right shape is part of
+    /// the combined surface, given whether the ray is currently inside the
+    /// left (`inl`) and right (`inr`) shapes.
+    pub fn allows(self, lhit: bool, inl: bool, inr: bool) -> bool {
+        match self {
+            CsgOperation::Union => (lhit && !inr) || (!lhit && !inl),
+            CsgOperation::Intersection => (lhit && inr) || (!lhit && inl),
+            CsgOperation::Difference => (lhit && !inr) || (!lhit && inl),
+        }
+    }
+}
+
+/// Constructive solid geometry: two shapes combined by an operation.
+#[derive(Debug)]
+pub struct Csg {
+    transform: Transformation,
+    operation: CsgOperation,
+    /// The left and the right shape.
+    children: [Box<dyn Shape>; 2],
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, left: Box<dyn Shape>, right: Box<dyn Shape>) -> Csg {
+        let mut csg = Csg {
+            transform: Transformation::identity(),
+            operation,
+            children: [left, right],
+        };
+        csg.update_children();
+        csg
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        self.update_children();
+        Ok(())
+    }
+
+    pub fn operation(&self) -> CsgOperation {
+        self.operation
+    }
+
+    pub fn left(&self) -> &dyn Shape {
+        self.children[0].as_ref()
+    }
+
+    pub fn right(&self) -> &dyn Shape {
+        self.children[1].as_ref()
+    }
+
+    /// Keeps only the intersections on the surface of the combined shape,
+    /// `xs` must be sorted by `t`.
+    pub fn filter_intersections<'a>(&self, xs: &[Intersection<'a>]) -> Vec<Intersection<'a>> {
+        let mut inl = false;
+        let mut inr = false;
+        let mut result = vec![];
+        for x in xs {
+            let lhit = self.left().includes(x.object);
+            if self.operation.allows(lhit, inl, inr) {
+                result.push(*x);
+            }
+            if lhit {
+                inl = !inl;
+            } else {
+                inr = !inr;
+            }
+        }
+        result
+    }
+
+    fn update_children(&mut self) {
+        for child in &mut self.children {
+            child.set_parent_transform(&self.transform);
+        }
+    }
+}
+
+impl Shape for Csg {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn material(&self) -> Material {
+        Material::new()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Transformation) {
+        self.transform.set_parent(parent);
+        self.update_children();
+    }
+
+    fn local_intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
+        let mut xs = self.left().intersect(r);
+        xs.extend(self.right().intersect(r));
+        xs.sort_by(|x, y| x.t.partial_cmp(&y.t).unwrap());
+        self.filter_intersections(&xs)
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.left()
+            .parent_space_bounds()
+            .merge(&self.right().parent_space_bounds())
+    }
+
+    fn local_normal_at(&self, _object_point: Point) -> Vector {
+        panic!("a Csg has no surface of its own, normals come from its leaves")
+    }
+
+    fn children(&self) -> &[Box<dyn Shape>] {
+        &self.children
+    }
+
+    fn includes(&self, other: &dyn Shape) -> bool {
+        self.children.iter().any(|child| child.includes(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::point::point;
+    use crate::sphere::Sphere;
+    use crate::test_utils::*;
+    use crate::transforms::translation;
+    use crate::vector::vector;
+
+    #[test]
+    fn test_create_csg() {
+        let c = Csg::new(
+            CsgOperation::Union,
+            Box::new(Sphere::new()),
+            Box::new(Cube::new()),
+        );
+        assert_eq!(c.operation(), CsgOperation::Union);
+        assert!(c.includes(c.left()));
+        assert!(c.includes(c.right()));
+        assert!(!c.includes(&Sphere::new()));
+        assert_eq!(c.children().len(), 2);
+        assert_eq!(c.children()[0].as_ref(), c.left());
+        assert_eq!(c.children()[1].as_ref(), c.right());
+    }
+
+    #[test]
+    fn test_evaluate_rule_for_csg_operation() {
+        use CsgOperation::*;
+        let cases = [
+            (Union, true, true, true, false),
+            (Union, true, true, false, true),
+            (Union, true, false, true, false),
+            (Union, true, false, false, true),
+            (Union, false, true, true, false),
+            (Union, false, true, false, false),
+            (Union, false, false, true, true),
+            (Union, false, false, false, true),
+            (Intersection, true, true, true, true),
+            (Intersection, true, true, false, false),
+            (Intersection, true, false, true, true),
+            (Intersection, true, false, false, false),
+            (Intersection, false, true, true, true),
+            (Intersection, false, true, false, true),
+            (Intersection, false, false, true, false),
+            (Intersection, false, false, false, false),
+            (Difference, true, true, true, false),
+            (Difference, true, true, false, true),
+            (Difference, true, false, true, false),
+            (Difference, true, false, false, true),
+            (Difference, false, true, true, true),
+            (Difference, false, true, false, true),
+            (Difference, false, false, true, false),
+            (Difference, false, false, false, false),
+        ];
+        for (op, lhit, inl, inr, result) in cases {
+            assert_eq!(op.allows(lhit, inl, inr), result);
+        }
+    }
+
+    #[test]
+    fn test_filter_list_of_intersections() {
+        let cases = [
+            (CsgOperation::Union, 0, 3),
+            (CsgOperation::Intersection, 1, 2),
+            (CsgOperation::Difference, 0, 1),
+        ];
+        for (op, x0, x1) in cases {
+            let c = Csg::new(op, Box::new(Sphere::new()), Box::new(Cube::new()));
+            let xs = vec![
+                Intersection::new(c.left(), 1.0),
+                Intersection::new(c.right(), 2.0),
+                Intersection::new(c.left(), 3.0),
+                Intersection::new(c.right(), 4.0),
+            ];
+            assert_eq!(c.filter_intersections(&xs), vec![xs[x0], xs[x1]]);
+        }
+    }
+
+    #[test]
+    fn test_ray_misses_csg() {
+        let c = Csg::new(
+            CsgOperation::Union,
+            Box::new(Sphere::new()),
+            Box::new(Cube::new()),
+        );
+        let r = Ray::new(point(0, 2, -5), vector(0, 0, 1));
+        assert!(c.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn test_ray_hits_csg() {
+        let c = Csg::new(
+            CsgOperation::Union,
+            Box::new(Sphere::new()),
+            Box::new({
+                let mut s = Sphere::new();
+                s.set_transform(translation(0.0, 0.0, 0.5)).unwrap();
+                s
+            }),
+        );
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let xs = c.local_intersect(&r);
+        assert_eq!(ts(xs.clone()), vec![4.0, 6.5]);
+        assert_eq!(xs[0].object, c.left());
+        assert_eq!(xs[1].object, c.right());
+    }
+
+    #[test]
+    fn test_difference_normal_inside_cavity() {
+        let c = Csg::new(
+            CsgOperation::Difference,
+            Box::new(Cube::new()),
+            Box::new({
+                let mut s = Sphere::new();
+                s.set_transform(translation(0.0, 0.0, -1.0)).unwrap();
+                s
+            }),
+        );
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let xs = c.intersect(&r);
+        assert_eq!(ts(xs.clone()), vec![5.0, 6.0]);
+        assert_eq!(xs[0].object, c.right());
+        assert_near!(xs[0].object.normal_at(point(0, 0, 0)), vector(0, 0, 1));
+    }
+}
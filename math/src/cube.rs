@@ -0,0 +1,172 @@
+use crate::bounds::Bounds;
+use crate::intersect::Intersection;
+use crate::material::Material;
+use crate::matrix::Matrix;
+use crate::point::{point, Point};
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::transformation::Transformation;
+use crate::vector::{vector, Vector};
+
+#[derive(Debug, PartialEq)]
+pub struct Cube {
+    transform: Transformation,
+    material: Material,
+}
+
+impl Cube {
+    pub fn new() -> Cube {
+        Cube {
+            transform: Transformation::identity(),
+            material: Material::new(),
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+}
+
+impl Default for Cube {
+    fn default() -> Self {
+        Cube::new()
+    }
+}
+
+impl Shape for Cube {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Transformation) {
+        self.transform.set_parent(parent);
+    }
+
+    fn local_intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
+        // The cube is its own bounding box.
+        match self.bounds().intersect(r) {
+            Some((tmin, tmax)) => {
+                vec![Intersection::new(self, tmin), Intersection::new(self, tmax)]
+            }
+            None => vec![],
+        }
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(point(-1, -1, -1), point(1, 1, 1))
+    }
+
+    fn local_normal_at(&self, p: Point) -> Vector {
+        let maxc = p.x.abs().max(p.y.abs()).max(p.z.abs());
+        if maxc == p.x.abs() {
+            vector(p.x, 0.0, 0.0)
+        } else if maxc == p.y.abs() {
+            vector(0.0, p.y, 0.0)
+        } else {
+            vector(0.0, 0.0, p.z)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::point;
+    use crate::test_utils::*;
+    use crate::transforms::{scaling, translation};
+
+    #[test]
+    fn test_ray_intersects_cube() {
+        let c = Cube::new();
+        let cases = [
+            (point(5.0, 0.5, 0.0), vector(-1, 0, 0), 4.0, 6.0),
+            (point(-5.0, 0.5, 0.0), vector(1, 0, 0), 4.0, 6.0),
+            (point(0.5, 5.0, 0.0), vector(0, -1, 0), 4.0, 6.0),
+            (point(0.5, -5.0, 0.0), vector(0, 1, 0), 4.0, 6.0),
+            (point(0.5, 0.0, 5.0), vector(0, 0, -1), 4.0, 6.0),
+            (point(0.5, 0.0, -5.0), vector(0, 0, 1), 4.0, 6.0),
+            (point(0.0, 0.5, 0.0), vector(0, 0, 1), -1.0, 1.0),
+        ];
+        for (origin, direction, t1, t2) in cases {
+            let r = Ray::new(origin, direction);
+            assert_eq!(ts(c.local_intersect(&r)), vec![t1, t2]);
+        }
+    }
+
+    #[test]
+    fn test_ray_misses_cube() {
+        let c = Cube::new();
+        let cases = [
+            (point(-2, 0, 0), vector(0.2673, 0.5345, 0.8018)),
+            (point(0, -2, 0), vector(0.8018, 0.2673, 0.5345)),
+            (point(0, 0, -2), vector(0.5345, 0.8018, 0.2673)),
+            (point(2, 0, 2), vector(0, 0, -1)),
+            (point(0, 2, 2), vector(0, -1, 0)),
+            (point(2, 2, 0), vector(-1, 0, 0)),
+        ];
+        for (origin, direction) in cases {
+            let r = Ray::new(origin, direction);
+            assert_eq!(ts(c.local_intersect(&r)), vec![]);
+        }
+    }
+
+    #[test]
+    fn test_ray_parallel_to_face_on_its_plane() {
+        let c = Cube::new();
+        let r = Ray::new(point(-5.0, 1.0, 0.0), vector(1, 0, 0));
+        assert_eq!(ts(c.local_intersect(&r)), vec![4.0, 6.0]);
+        let r = Ray::new(point(1.0, -1.0, -5.0), vector(0, 0, 1));
+        assert_eq!(ts(c.local_intersect(&r)), vec![4.0, 6.0]);
+    }
+
+    #[test]
+    fn test_normal_on_surface_of_cube() {
+        let c = Cube::new();
+        let cases = [
+            (point(1.0, 0.5, -0.8), vector(1, 0, 0)),
+            (point(-1.0, -0.2, 0.9), vector(-1, 0, 0)),
+            (point(-0.4, 1.0, -0.1), vector(0, 1, 0)),
+            (point(0.3, -1.0, -0.7), vector(0, -1, 0)),
+            (point(-0.6, 0.3, 1.0), vector(0, 0, 1)),
+            (point(0.4, 0.4, -1.0), vector(0, 0, -1)),
+            (point(1, 1, 1), vector(1, 0, 0)),
+            (point(-1, -1, -1), vector(-1, 0, 0)),
+        ];
+        for (p, n) in cases {
+            assert_eq!(c.local_normal_at(p), n);
+        }
+    }
+
+    #[test]
+    fn test_intersect_transformed_cube() {
+        let mut c = Cube::new();
+        c.set_transform(&translation(0., 0., 5.) * &scaling(2., 2., 2.))
+            .unwrap();
+        let r = Ray::new(point(0, 0, 0), vector(0, 0, 1));
+        assert_eq!(ts(c.intersect(&r)), vec![3.0, 7.0]);
+        assert_near!(c.normal_at(point(0, 0, 3)), vector(0, 0, -1));
+    }
+
+    #[test]
+    fn test_default_material() {
+        assert_eq!(Cube::new().material(), Material::new());
+    }
+
+    #[test]
+    fn test_assigned_material() {
+        let mut c = Cube::new();
+        let mut m = Material::new();
+        m.ambient = 1.0;
+        c.set_material(m.clone());
+        assert_eq!(c.material(), m);
+    }
+}
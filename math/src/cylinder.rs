@@ -0,0 +1,273 @@
+use crate::approx::EPSILON;
+use crate::bounds::Bounds;
+use crate::intersect::Intersection;
+use crate::material::Material;
+use crate::matrix::Matrix;
+use crate::point::{point, Point};
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::transformation::Transformation;
+use crate::vector::{vector, Vector};
+
+/// A cylinder of radius 1 around the y axis, truncated to
+/// `minimum < y < maximum` and optionally capped at both ends.
+#[derive(Debug, PartialEq)]
+pub struct Cylinder {
+    transform: Transformation,
+    material: Material,
+    pub minimum: f32,
+    pub maximum: f32,
+    pub closed: bool,
+}
+
+impl Cylinder {
+    pub fn new() -> Cylinder {
+        Cylinder {
+            transform: Transformation::identity(),
+            material: Material::new(),
+            minimum: f32::NEG_INFINITY,
+            maximum: f32::INFINITY,
+            closed: false,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn intersect_caps<'a>(&'a self, r: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        if !self.closed || r.direction.y.abs() < EPSILON {
+            return;
+        }
+        for y in [self.minimum, self.maximum] {
+            let t = (y - r.origin.y) / r.direction.y;
+            if check_cap(r, t, 1.0) {
+                xs.push(Intersection::new(self, t));
+            }
+        }
+    }
+}
+
+impl Default for Cylinder {
+    fn default() -> Self {
+        Cylinder::new()
+    }
+}
+
+/// Checks that the ray at `t` is within `radius` from the y axis.
+pub(crate) fn check_cap(r: &Ray, t: f32, radius: f32) -> bool {
+    let x = r.origin.x + t * r.direction.x;
+    let z = r.origin.z + t * r.direction.z;
+    x * x + z * z <= radius * radius + EPSILON
+}
+
+impl Shape for Cylinder {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Transformation) {
+        self.transform.set_parent(parent);
+    }
+
+    fn local_intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
+        let mut xs = vec![];
+
+        let a = r.direction.x * r.direction.x + r.direction.z * r.direction.z;
+        if a.abs() >= EPSILON {
+            let b = 2.0 * (r.origin.x * r.direction.x + r.origin.z * r.direction.z);
+            let c = r.origin.x * r.origin.x + r.origin.z * r.origin.z - 1.0;
+            let disc = b * b - 4.0 * a * c;
+            if disc < 0.0 {
+                return xs;
+            }
+
+            let t0 = (-b - disc.sqrt()) / (2.0 * a);
+            let t1 = (-b + disc.sqrt()) / (2.0 * a);
+            for t in [t0.min(t1), t0.max(t1)] {
+                let y = r.origin.y + t * r.direction.y;
+                if self.minimum < y && y < self.maximum {
+                    xs.push(Intersection::new(self, t));
+                }
+            }
+        }
+
+        self.intersect_caps(r, &mut xs);
+        xs
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            point(-1.0, self.minimum, -1.0),
+            point(1.0, self.maximum, 1.0),
+        )
+    }
+
+    fn local_normal_at(&self, p: Point) -> Vector {
+        let dist = p.x * p.x + p.z * p.z;
+        if dist < 1.0 && p.y >= self.maximum - EPSILON {
+            vector(0, 1, 0)
+        } else if dist < 1.0 && p.y <= self.minimum + EPSILON {
+            vector(0, -1, 0)
+        } else {
+            vector(p.x, 0.0, p.z)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::point;
+    use crate::test_utils::*;
+    use crate::transforms::scaling;
+
+    #[test]
+    fn test_ray_misses_cylinder() {
+        let cyl = Cylinder::new();
+        let cases = [
+            (point(1, 0, 0), vector(0, 1, 0)),
+            (point(0, 0, 0), vector(0, 1, 0)),
+            (point(0, 0, -5), vector(1, 1, 1)),
+        ];
+        for (origin, direction) in cases {
+            let r = Ray::new(origin, direction.normalized());
+            assert_eq!(ts(cyl.local_intersect(&r)), vec![]);
+        }
+    }
+
+    #[test]
+    fn test_ray_strikes_cylinder() {
+        let cyl = Cylinder::new();
+        let cases = [
+            (point(1, 0, -5), vector(0, 0, 1), 5.0, 5.0),
+            (point(0, 0, -5), vector(0, 0, 1), 4.0, 6.0),
+            (
+                point(0.5, 0.0, -5.0),
+                vector(0.1, 1.0, 1.0),
+                6.80798,
+                7.08872,
+            ),
+        ];
+        for (origin, direction, t0, t1) in cases {
+            let r = Ray::new(origin, direction.normalized());
+            let xs = ts(cyl.local_intersect(&r));
+            assert_eq!(xs.len(), 2);
+            assert_near!(xs[0], t0, 1e-4);
+            assert_near!(xs[1], t1, 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_normal_on_cylinder() {
+        let cyl = Cylinder::new();
+        let cases = [
+            (point(1, 0, 0), vector(1, 0, 0)),
+            (point(0, 5, -1), vector(0, 0, -1)),
+            (point(0, -2, 1), vector(0, 0, 1)),
+            (point(-1, 1, 0), vector(-1, 0, 0)),
+        ];
+        for (p, n) in cases {
+            assert_eq!(cyl.local_normal_at(p), n);
+        }
+    }
+
+    #[test]
+    fn test_default_cylinder_is_infinite_and_open() {
+        let cyl = Cylinder::new();
+        assert_eq!(cyl.minimum, f32::NEG_INFINITY);
+        assert_eq!(cyl.maximum, f32::INFINITY);
+        assert!(!cyl.closed);
+    }
+
+    #[test]
+    fn test_intersect_truncated_cylinder() {
+        let mut cyl = Cylinder::new();
+        cyl.minimum = 1.0;
+        cyl.maximum = 2.0;
+        let cases = [
+            (point(0.0, 1.5, 0.0), vector(0.1, 1.0, 0.0), 0),
+            (point(0, 3, -5), vector(0, 0, 1), 0),
+            (point(0, 0, -5), vector(0, 0, 1), 0),
+            (point(0, 2, -5), vector(0, 0, 1), 0),
+            (point(0, 1, -5), vector(0, 0, 1), 0),
+            (point(0.0, 1.5, -2.0), vector(0, 0, 1), 2),
+        ];
+        for (origin, direction, count) in cases {
+            let r = Ray::new(origin, direction.normalized());
+            assert_eq!(cyl.local_intersect(&r).len(), count);
+        }
+    }
+
+    #[test]
+    fn test_intersect_caps_of_closed_cylinder() {
+        let mut cyl = Cylinder::new();
+        cyl.minimum = 1.0;
+        cyl.maximum = 2.0;
+        cyl.closed = true;
+        let cases = [
+            (point(0, 3, 0), vector(0, -1, 0), 2),
+            (point(0, 3, -2), vector(0, -1, 2), 2),
+            (point(0, 4, -2), vector(0, -1, 1), 2),
+            (point(0, 0, -2), vector(0, 1, 2), 2),
+            (point(0, -1, -2), vector(0, 1, 1), 2),
+        ];
+        for (origin, direction, count) in cases {
+            let r = Ray::new(origin, direction.normalized());
+            assert_eq!(cyl.local_intersect(&r).len(), count);
+        }
+    }
+
+    #[test]
+    fn test_normal_on_cylinder_end_caps() {
+        let mut cyl = Cylinder::new();
+        cyl.minimum = 1.0;
+        cyl.maximum = 2.0;
+        cyl.closed = true;
+        let cases = [
+            (point(0, 1, 0), vector(0, -1, 0)),
+            (point(0.5, 1.0, 0.0), vector(0, -1, 0)),
+            (point(0.0, 1.0, 0.5), vector(0, -1, 0)),
+            (point(0, 2, 0), vector(0, 1, 0)),
+            (point(0.5, 2.0, 0.0), vector(0, 1, 0)),
+            (point(0.0, 2.0, 0.5), vector(0, 1, 0)),
+        ];
+        for (p, n) in cases {
+            assert_eq!(cyl.local_normal_at(p), n);
+        }
+    }
+
+    #[test]
+    fn test_intersect_scaled_cylinder() {
+        let mut cyl = Cylinder::new();
+        cyl.set_transform(scaling(2., 1., 2.)).unwrap();
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        assert_eq!(ts(cyl.intersect(&r)), vec![3.0, 7.0]);
+    }
+
+    #[test]
+    fn test_assigned_material() {
+        let mut cyl = Cylinder::new();
+        let mut m = Material::new();
+        m.ambient = 1.0;
+        cyl.set_material(m.clone());
+        assert_eq!(cyl.material(), m);
+    }
+
+    #[test]
+    fn test_truncated_cylinder_bounds() {
+        let mut c = Cylinder::new();
+        c.minimum = -5.0;
+        c.maximum = 3.0;
+        assert_eq!(c.bounds(), Bounds::new(point(-1, -5, -1), point(1, 3, 1)));
+    }
+}
@@ -0,0 +1,297 @@
+use crate::bounds::Bounds;
+use crate::bvh::Bvh;
+use crate::intersect::Intersection;
+use crate::material::Material;
+use crate::matrix::Matrix;
+use crate::point::Point;
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::transformation::Transformation;
+use crate::vector::Vector;
+use std::cell::OnceCell;
+
+/// A collection of shapes transformed together.
+///
+/// Children keep their own transforms, relative to the group. Rays are
+/// tested against the children through a `Bvh`, built on first use.
+#[derive(Debug)]
+pub struct Group {
+    transform: Transformation,
+    children: Vec<Box<dyn Shape>>,
+    bvh: OnceCell<Bvh>,
+}
+
+impl Group {
+    pub fn new() -> Group {
+        Group {
+            transform: Transformation::identity(),
+            children: vec![],
+            bvh: OnceCell::new(),
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        self.update_children();
+        Ok(())
+    }
+
+    pub fn add_child(&mut self, mut child: Box<dyn Shape>) {
+        child.set_parent_transform(&self.transform);
+        self.children.push(child);
+        self.bvh.take();
+    }
+
+    pub fn children(&self) -> &[Box<dyn Shape>] {
+        &self.children
+    }
+
+    fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| Bvh::build(&self.children))
+    }
+
+    fn update_children(&mut self) {
+        for child in &mut self.children {
+            child.set_parent_transform(&self.transform);
+        }
+    }
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Group::new()
+    }
+}
+
+impl Shape for Group {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn material(&self) -> Material {
+        Material::new()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Transformation) {
+        self.transform.set_parent(parent);
+        self.update_children();
+    }
+
+    fn local_intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
+        let mut xs = self.bvh().intersect(&self.children, r);
+        xs.sort_by(|x, y| x.t.partial_cmp(&y.t).unwrap());
+        xs
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.children.iter().fold(Bounds::empty(), |b, child| {
+            b.merge(&child.parent_space_bounds())
+        })
+    }
+
+    fn local_normal_at(&self, _object_point: Point) -> Vector {
+        panic!("a Group has no surface of its own, normals come from its leaves")
+    }
+
+    fn children(&self) -> &[Box<dyn Shape>] {
+        &self.children
+    }
+
+    fn includes(&self, other: &dyn Shape) -> bool {
+        self.children.iter().any(|child| child.includes(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::point;
+    use crate::sphere::Sphere;
+    use crate::test_utils::*;
+    use crate::transforms::{rotation_y, scaling, translation};
+    use crate::vector::vector;
+    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+    #[test]
+    fn test_create_group() {
+        let g = Group::new();
+        assert_eq!(g.transform().matrix(), &Matrix::identity());
+        assert!(g.children().is_empty());
+    }
+
+    #[test]
+    fn test_add_child_to_group() {
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::new()));
+        assert_eq!(g.children().len(), 1);
+    }
+
+    #[test]
+    fn test_intersect_empty_group() {
+        let g = Group::new();
+        let r = Ray::new(point(0, 0, 0), vector(0, 0, 1));
+        assert!(g.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn test_intersect_nonempty_group() {
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::new()));
+        g.add_child(Box::new({
+            let mut s = Sphere::new();
+            s.set_transform(translation(0., 0., -3.)).unwrap();
+            s
+        }));
+        g.add_child(Box::new({
+            let mut s = Sphere::new();
+            s.set_transform(translation(5., 0., 0.)).unwrap();
+            s
+        }));
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let xs = g.local_intersect(&r);
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs[0].object, g.children()[1].as_ref());
+        assert_eq!(xs[1].object, g.children()[1].as_ref());
+        assert_eq!(xs[2].object, g.children()[0].as_ref());
+        assert_eq!(xs[3].object, g.children()[0].as_ref());
+    }
+
+    #[test]
+    fn test_intersect_transformed_group() {
+        let mut g = Group::new();
+        g.set_transform(scaling(2., 2., 2.)).unwrap();
+        g.add_child(Box::new({
+            let mut s = Sphere::new();
+            s.set_transform(translation(5., 0., 0.)).unwrap();
+            s
+        }));
+        let r = Ray::new(point(10, 0, -10), vector(0, 0, 1));
+        assert_eq!(g.intersect(&r).len(), 2);
+    }
+
+    fn nested_sphere(g2_transform: Matrix<4, 4>) -> Group {
+        let mut g2 = Group::new();
+        g2.set_transform(g2_transform).unwrap();
+        g2.add_child(Box::new({
+            let mut s = Sphere::new();
+            s.set_transform(translation(5., 0., 0.)).unwrap();
+            s
+        }));
+        let mut g1 = Group::new();
+        g1.set_transform(rotation_y(PI / 2.0)).unwrap();
+        g1.add_child(Box::new(g2));
+        g1
+    }
+
+    #[test]
+    fn test_convert_point_from_world_to_object_space() {
+        let g1 = nested_sphere(scaling(2., 2., 2.));
+        let r = Ray::new(point(0, 0, -20), vector(0, 0, 1));
+        let xs = g1.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_near!(
+            xs[0].object.world_to_object(point(-2, 0, -10)),
+            point(0, 0, -1)
+        );
+    }
+
+    #[test]
+    fn test_convert_normal_from_object_to_world_space() {
+        let g1 = nested_sphere(scaling(1., 2., 3.));
+        let r = Ray::new(point(0, 0, -20), vector(0, 0, 1));
+        let xs = g1.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        let a = 3.0_f32.sqrt() / 3.0;
+        assert_near!(
+            xs[0].object.normal_to_world(vector(a, a, a)),
+            vector(0.28571, 0.42857, -0.85714)
+        );
+    }
+
+    #[test]
+    fn test_normal_on_child_object() {
+        let g1 = nested_sphere(scaling(1., 2., 3.));
+        let r = Ray::new(point(0, 0, -20), vector(0, 0, 1));
+        let xs = g1.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_near!(
+            xs[0].object.normal_at(point(1.7321, 1.1547, -5.5774)),
+            vector(0.2857, 0.42854, -0.85716),
+            1e-4
+        );
+    }
+
+    #[test]
+    fn test_group_includes_descendants() {
+        let mut g2 = Group::new();
+        g2.add_child(Box::new(Sphere::new()));
+        let mut g1 = Group::new();
+        g1.add_child(Box::new(g2));
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let xs = g1.intersect(&r);
+        assert!(g1.includes(xs[0].object));
+        assert!(g1.children()[0].includes(xs[0].object));
+        assert!(!g1.includes(&Sphere::new()));
+    }
+
+    #[test]
+    fn test_transform_group_after_adding_children() {
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::new()));
+        g.set_transform(translation(0., 0., 10.)).unwrap();
+        let s = g.children()[0].as_ref();
+        assert_near!(s.world_to_object(point(0, 0, 10)), point(0, 0, 0));
+        assert_near!(
+            s.normal_at(point(FRAC_1_SQRT_2, 0.0, 10.0 - FRAC_1_SQRT_2)),
+            vector(FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2)
+        );
+    }
+
+    #[test]
+    fn test_group_bounds_contain_children() {
+        let mut g = Group::new();
+        g.add_child(Box::new({
+            let mut s = Sphere::new();
+            s.set_transform(&translation(2., 5., -3.) * &scaling(2., 2., 2.))
+                .unwrap();
+            s
+        }));
+        g.add_child(Box::new({
+            let mut s = Sphere::new();
+            s.set_transform(translation(-4., -1., 4.)).unwrap();
+            s
+        }));
+        assert_eq!(g.bounds(), Bounds::new(point(-5, -2, -5), point(4, 7, 5)));
+    }
+
+    #[test]
+    fn test_intersect_group_after_adding_more_children() {
+        let mut g = Group::new();
+        for i in 0..10 {
+            g.add_child(Box::new({
+                let mut s = Sphere::new();
+                s.set_transform(translation(3. * i as f32, 0., 0.)).unwrap();
+                s
+            }));
+        }
+        let r = Ray::new(point(30, 0, -5), vector(0, 0, 1));
+        assert!(g.intersect(&r).is_empty());
+        g.add_child(Box::new({
+            let mut s = Sphere::new();
+            s.set_transform(translation(30., 0., 0.)).unwrap();
+            s
+        }));
+        let xs = g.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].object, g.children()[10].as_ref());
+    }
+
+    #[test]
+    fn test_group_children_through_shape() {
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::new()));
+        let shape: &dyn Shape = &g;
+        assert_eq!(shape.children().len(), 1);
+        assert!(Sphere::new().children().is_empty());
+    }
+}
@@ -1,14 +1,21 @@
-use crate::world::WorldObject;
+use crate::shape::Shape;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Intersection<'a> {
-    pub object: &'a WorldObject,
+    pub object: &'a dyn Shape,
     pub t: f32,
+    /// Barycentric coordinates of the hit, only set by triangles.
+    pub u: f32,
+    pub v: f32,
 }
 
 impl<'a> Intersection<'a> {
-    pub fn new(object: &'a WorldObject, t: f32) -> Self {
-        Self { object, t }
+    pub fn new(object: &'a dyn Shape, t: f32) -> Self {
+        Self::with_uv(object, t, 0.0, 0.0)
+    }
+
+    pub fn with_uv(object: &'a dyn Shape, t: f32, u: f32, v: f32) -> Self {
+        Self { object, t, u, v }
     }
 }
 
@@ -23,19 +30,18 @@ pub fn hit<'a>(xs: &[Intersection<'a>]) -> Option<Intersection<'a>> {
 mod tests {
     use super::*;
     use crate::sphere::Sphere;
-    use crate::world::WorldObject;
 
     #[test]
     fn test_create_intersection() {
-        let s = WorldObject::Sphere(Sphere::new());
+        let s = Sphere::new();
         let i = Intersection::new(&s, 3.5);
-        assert_eq!(i.object, &s);
+        assert_eq!(i.object, &s as &dyn Shape);
         assert_eq!(i.t, 3.5);
     }
 
     #[test]
     fn test_create_intersections() {
-        let s = WorldObject::Sphere(Sphere::new());
+        let s = Sphere::new();
         let i1 = Intersection::new(&s, 1.0);
         let i2 = Intersection::new(&s, 2.0);
         let xs: Vec<Intersection> = vec![i1, i2];
@@ -46,7 +52,7 @@ mod tests {
 
     #[test]
     fn test_hit_all_positive() {
-        let s = WorldObject::Sphere(Sphere::new());
+        let s = Sphere::new();
         let i1 = Intersection::new(&s, 1.0);
         let i2 = Intersection::new(&s, 2.0);
         let xs: Vec<Intersection> = vec![i2, i1];
@@ -55,7 +61,7 @@ mod tests {
 
     #[test]
     fn test_hit_mixed() {
-        let s = WorldObject::Sphere(Sphere::new());
+        let s = Sphere::new();
         let i1 = Intersection::new(&s, -1.0);
         let i2 = Intersection::new(&s, 1.0);
         let xs: Vec<Intersection> = vec![i2, i1];
@@ -64,7 +70,7 @@ mod tests {
 
     #[test]
     fn test_hit_all_negative() {
-        let s = WorldObject::Sphere(Sphere::new());
+        let s = Sphere::new();
         let i1 = Intersection::new(&s, -2.0);
         let i2 = Intersection::new(&s, -1.0);
         let xs: Vec<Intersection> = vec![i2, i1];
@@ -73,7 +79,7 @@ mod tests {
 
     #[test]
     fn test_hit_returns_lowest_non_negative() {
-        let s = WorldObject::Sphere(Sphere::new());
+        let s = Sphere::new();
         let i1 = Intersection::new(&s, 5.0);
         let i2 = Intersection::new(&s, 7.0);
         let i3 = Intersection::new(&s, -3.0);
@@ -81,4 +87,13 @@ mod tests {
         let xs: Vec<Intersection> = vec![i1, i2, i3, i4];
         assert_eq!(hit(&xs), Some(i4));
     }
+
+    #[test]
+    fn test_create_intersection_with_uv() {
+        let s = Sphere::new();
+        let i = Intersection::with_uv(&s, 3.5, 0.2, 0.4);
+        assert_eq!(i.t, 3.5);
+        assert_eq!(i.u, 0.2);
+        assert_eq!(i.v, 0.4);
+    }
 }
@@ -1,18 +1,38 @@
 pub mod approx;
+pub mod background;
+pub mod bounds;
+pub mod bvh;
 pub mod camera;
 pub mod canvas;
 pub mod color;
+pub mod cone;
+pub mod csg;
+pub mod cube;
+pub mod cylinder;
+pub mod group;
 pub mod image;
 pub mod intersect;
 pub mod light;
 pub mod lighting;
 pub mod material;
 pub mod matrix;
+pub mod noise;
+pub mod obj;
+pub mod path_tracer;
+pub mod pattern;
+pub mod plane;
 pub mod point;
+pub mod random;
 pub mod ray;
+pub mod sampling;
+pub mod shader;
+pub mod shape;
 pub mod sphere;
+pub mod transformation;
 pub mod transforms;
+pub mod triangle;
 pub mod tuple;
+pub mod uv;
 pub mod vector;
 pub mod view;
 pub mod world;
@@ -1,5 +1,72 @@
+use crate::approx::EPSILON;
+use crate::background::EnvironmentMap;
 use crate::color::Color;
 use crate::point::Point;
+use crate::random::Rng;
+use crate::sampling;
+use crate::vector::{cross, dot, Vector};
+use std::f32::consts::PI;
+use std::fmt::Debug;
+
+/// Light arriving at a point from a single point on a light.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct LightSample {
+    /// Unit vector from the lit point towards the light.
+    pub direction: Vector,
+    /// Distance to the light, infinite for directional lights.
+    pub distance: f32,
+    /// Color and brightness of the light reaching the point.
+    pub intensity: Color,
+}
+
+impl LightSample {
+    fn towards(position: Point, point: Point, intensity: Color) -> LightSample {
+        let v = position - point;
+        LightSample {
+            direction: v.normalized(),
+            distance: v.len(),
+            intensity,
+        }
+    }
+}
+
+pub trait Light: Debug {
+    /// Color and brightness of the light at its source, used for ambient
+    /// lighting.
+    fn intensity(&self) -> Color;
+
+    /// The light arriving at `point`, one sample per point on the light that
+    /// is shaded from and tested for shadows. Lights that jitter their
+    /// samples draw from `rng`.
+    fn samples_at(&self, point: Point, rng: &mut Rng) -> Vec<LightSample>;
+
+    /// Picks a single random point on the light for Monte Carlo estimators,
+    /// `u1` and `u2` being uniform in `[0, 1)`. Returns the sample with its
+    /// probability density over solid angle, or with `None` for lights that
+    /// are a single point or direction. A light without samples gives a
+    /// black one. By default this picks one of `samples_at`, jittered by a
+    /// generator seeded from `u2`.
+    fn sample(&self, point: Point, u1: f32, u2: f32) -> (LightSample, Option<f32>) {
+        let samples = self.samples_at(point, &mut Rng::new(u2.to_bits() as u64));
+        if samples.is_empty() {
+            let sample = LightSample {
+                direction: Vector::new(0.0, 0.0, 0.0),
+                distance: 0.0,
+                intensity: Color::black(),
+            };
+            return (sample, None);
+        }
+        let i = ((u1 * samples.len() as f32) as usize).min(samples.len() - 1);
+        (samples[i], None)
+    }
+
+    /// Returns where a ray from `point` along `direction` meets the light,
+    /// with the density of `sample` picking it, or `None` if it misses. Rays
+    /// never meet lights that are a single point or direction.
+    fn hit(&self, _point: Point, _direction: Vector) -> Option<(LightSample, f32)> {
+        None
+    }
+}
 
 #[derive(PartialEq, Debug)]
 pub struct PointLight {
@@ -16,10 +83,433 @@ impl PointLight {
     }
 }
 
+impl Light for PointLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples_at(&self, point: Point, _rng: &mut Rng) -> Vec<LightSample> {
+        vec![LightSample::towards(self.position, point, self.intensity)]
+    }
+}
+
+/// A point light that only shines into a cone, fading out smoothly between
+/// the inner and the outer angle.
+#[derive(PartialEq, Debug)]
+pub struct SpotLight {
+    pub position: Point,
+    /// Unit vector along the axis of the cone.
+    pub direction: Vector,
+    /// Angle from the axis, in radians, up to which the light is at full
+    /// intensity.
+    pub inner_angle: f32,
+    /// Angle from the axis, in radians, beyond which there is no light.
+    pub outer_angle: f32,
+    pub intensity: Color,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point,
+        direction: Vector,
+        inner_angle: f32,
+        outer_angle: f32,
+        intensity: Color,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            direction: direction.normalized(),
+            inner_angle,
+            outer_angle,
+            intensity,
+        }
+    }
+
+    /// Returns how much of the light reaches `point`, from 1 inside the
+    /// inner cone to 0 outside the outer cone.
+    pub fn falloff(&self, point: Point) -> f32 {
+        let cos_angle = dot((point - self.position).normalized(), self.direction);
+        let cos_inner = self.inner_angle.cos();
+        let cos_outer = self.outer_angle.cos();
+        if cos_angle >= cos_inner {
+            1.0
+        } else if cos_angle <= cos_outer {
+            0.0
+        } else {
+            let t = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples_at(&self, point: Point, _rng: &mut Rng) -> Vec<LightSample> {
+        let intensity = self.falloff(point) * self.intensity;
+        vec![LightSample::towards(self.position, point, intensity)]
+    }
+}
+
+/// A light infinitely far away, like the sun, whose rays are all parallel.
+#[derive(PartialEq, Debug)]
+pub struct DirectionalLight {
+    /// Unit vector in the direction the light travels.
+    pub direction: Vector,
+    pub intensity: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vector, intensity: Color) -> DirectionalLight {
+        DirectionalLight {
+            direction: direction.normalized(),
+            intensity,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples_at(&self, _point: Point, _rng: &mut Rng) -> Vec<LightSample> {
+        vec![LightSample {
+            direction: -self.direction,
+            distance: f32::INFINITY,
+            intensity: self.intensity,
+        }]
+    }
+}
+
+/// A rectangular light made of `usteps` by `vsteps` cells, which casts soft
+/// shadows by sampling a point in each cell.
+///
+/// Lights made with `new` put a point light of `intensity` in each cell.
+/// Lights made with `with_radiance` are a rectangle with sides perpendicular
+/// to each other that emits `radiance` from both of its faces, so that they
+/// dim with distance and with the angle they are seen at, and rays can `hit`
+/// them.
+#[derive(PartialEq, Debug)]
+pub struct AreaLight {
+    pub corner: Point,
+    /// Edge of a single cell.
+    pub uvec: Vector,
+    pub usteps: usize,
+    pub vvec: Vector,
+    pub vsteps: usize,
+    pub intensity: Color,
+    /// Radiance emitted from each point of the light, if it has one.
+    pub radiance: Option<Color>,
+    /// Samples a random point in each cell instead of its center, which
+    /// trades banding in the shadows for noise.
+    pub jitter: bool,
+}
+
+impl AreaLight {
+    /// Creates a light spanning `full_uvec` and `full_vvec` from `corner`.
+    pub fn new(
+        corner: Point,
+        full_uvec: Vector,
+        usteps: usize,
+        full_vvec: Vector,
+        vsteps: usize,
+        intensity: Color,
+    ) -> Result<AreaLight, &'static str> {
+        if usteps == 0 || vsteps == 0 {
+            return Err("Area light has no cells");
+        }
+        Ok(AreaLight {
+            corner,
+            uvec: full_uvec / usteps as f32,
+            usteps,
+            vvec: full_vvec / vsteps as f32,
+            vsteps,
+            intensity,
+            radiance: None,
+            jitter: true,
+        })
+    }
+
+    /// Creates a light emitting `radiance`, with no ambient light.
+    pub fn with_radiance(
+        corner: Point,
+        full_uvec: Vector,
+        usteps: usize,
+        full_vvec: Vector,
+        vsteps: usize,
+        radiance: Color,
+    ) -> Result<AreaLight, &'static str> {
+        let mut light =
+            AreaLight::new(corner, full_uvec, usteps, full_vvec, vsteps, Color::black())?;
+        light.radiance = Some(radiance);
+        Ok(light)
+    }
+
+    /// The center of the light.
+    pub fn position(&self) -> Point {
+        self.corner
+            + self.uvec * (self.usteps as f32 / 2.0)
+            + self.vvec * (self.vsteps as f32 / 2.0)
+    }
+
+    pub fn point_on_light(&self, u: usize, v: usize, rng: &mut Rng) -> Point {
+        let (du, dv) = if self.jitter {
+            (rng.next_f32(), rng.next_f32())
+        } else {
+            (0.5, 0.5)
+        };
+        self.corner + self.uvec * (u as f32 + du) + self.vvec * (v as f32 + dv)
+    }
+
+    pub fn samples(&self, rng: &mut Rng) -> Vec<Point> {
+        let mut samples = Vec::with_capacity(self.usteps * self.vsteps);
+        for v in 0..self.vsteps {
+            for u in 0..self.usteps {
+                samples.push(self.point_on_light(u, v, rng));
+            }
+        }
+        samples
+    }
+
+    fn full_uvec(&self) -> Vector {
+        self.uvec * self.usteps as f32
+    }
+
+    fn full_vvec(&self) -> Vector {
+        self.vvec * self.vsteps as f32
+    }
+
+    /// Density over solid angle of a point of the light seen from `point`,
+    /// for points sampled uniformly over the area.
+    fn pdf(&self, direction: Vector, distance: f32) -> f32 {
+        let normal = cross(self.full_uvec(), self.full_vvec());
+        let area = normal.len();
+        let cos = dot(normal.normalized(), direction).abs();
+        distance * distance / (area * cos)
+    }
+}
+
+impl Light for AreaLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples_at(&self, point: Point, rng: &mut Rng) -> Vec<LightSample> {
+        let samples = self.samples(rng).into_iter();
+        match self.radiance {
+            None => samples
+                .map(|sample| LightSample::towards(sample, point, self.intensity))
+                .collect(),
+            // Scaled like `EnvironmentLight::samples_at`, so that the diffuse
+            // term of `lighting::phong` matches the light reflected by a
+            // Lambertian surface.
+            Some(radiance) => samples
+                .map(|sample| {
+                    let mut sample = LightSample::towards(sample, point, radiance);
+                    let pdf = self.pdf(sample.direction, sample.distance);
+                    sample.intensity = (1.0 / (PI * pdf)) * radiance;
+                    sample
+                })
+                .collect(),
+        }
+    }
+
+    fn sample(&self, point: Point, u1: f32, u2: f32) -> (LightSample, Option<f32>) {
+        let position = self.corner + self.full_uvec() * u1 + self.full_vvec() * u2;
+        match self.radiance {
+            None => (LightSample::towards(position, point, self.intensity), None),
+            Some(radiance) => {
+                let sample = LightSample::towards(position, point, radiance);
+                (sample, Some(self.pdf(sample.direction, sample.distance)))
+            }
+        }
+    }
+
+    fn hit(&self, point: Point, direction: Vector) -> Option<(LightSample, f32)> {
+        let radiance = self.radiance?;
+        let (full_uvec, full_vvec) = (self.full_uvec(), self.full_vvec());
+        let normal = cross(full_uvec, full_vvec);
+        let denom = dot(direction, normal);
+        if denom.abs() < EPSILON {
+            return None;
+        }
+        let t = dot(self.corner - point, normal) / denom;
+        if t <= 0.0 {
+            return None;
+        }
+        let offset = (point + direction * t) - self.corner;
+        let u = dot(offset, full_uvec) / full_uvec.sq_len();
+        let v = dot(offset, full_vvec) / full_vvec.sq_len();
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+        let sample = LightSample {
+            direction,
+            distance: t,
+            intensity: radiance,
+        };
+        Some((sample, self.pdf(direction, t)))
+    }
+}
+
+/// Light from an environment map infinitely far away, like a studio HDRI,
+/// with `map` as the radiance arriving from each direction.
+///
+/// Directions are picked in proportion to the luminance of the map, so that
+/// a small bright window or sun gets most of the samples and casts sharp
+/// shadows. `samples_at` returns `samples` of them, each with an intensity
+/// scaled so that the diffuse term of `lighting::phong` averages to the
+/// light reflected by a Lambertian surface with an albedo of `diffuse`.
+#[derive(PartialEq, Debug)]
+pub struct EnvironmentLight {
+    pub map: EnvironmentMap,
+    samples: usize,
+    /// Mean radiance over all directions.
+    average: Color,
+    /// Cumulative distribution over the rows of the map.
+    rows: Vec<f32>,
+    /// Cumulative distribution over the pixels of each row.
+    columns: Vec<Vec<f32>>,
+}
+
+impl EnvironmentLight {
+    pub fn new(map: EnvironmentMap) -> EnvironmentLight {
+        let (width, height) = (map.canvas.width, map.canvas.height);
+        // Pixels near the poles cover less of the sphere.
+        let sin = |y: usize| ((y as f32 + 0.5) / height as f32 * PI).sin();
+        let mut average = Color::black();
+        let mut weights = vec![0.0; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let c = map.canvas.get(x, y);
+                average = average + sin(y) * c;
+                weights[y * width + x] = c.luminance().max(0.0) * sin(y);
+            }
+        }
+        // A black map gives no light, but still needs a distribution.
+        if weights.iter().all(|&w| w == 0.0) {
+            for (i, w) in weights.iter_mut().enumerate() {
+                *w = sin(i / width);
+            }
+        }
+        let sin_total: f32 = (0..height).map(sin).sum();
+        let average = (1.0 / (sin_total * width as f32)) * average;
+
+        let mut rows = Vec::with_capacity(height);
+        let mut columns = Vec::with_capacity(height);
+        let mut total = 0.0;
+        for row in weights.chunks(width) {
+            let mut cdf = Vec::with_capacity(width);
+            let mut row_total = 0.0;
+            for w in row {
+                row_total += w;
+                cdf.push(row_total);
+            }
+            for c in &mut cdf {
+                *c /= row_total.max(f32::MIN_POSITIVE);
+            }
+            total += row_total;
+            rows.push(total);
+            columns.push(cdf);
+        }
+        for r in &mut rows {
+            *r /= total;
+        }
+
+        EnvironmentLight {
+            map,
+            samples: 16,
+            average,
+            rows,
+            columns,
+        }
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Sets how many directions `samples_at` returns, at least one.
+    pub fn set_samples(&mut self, samples: usize) -> Result<(), &'static str> {
+        if samples == 0 {
+            return Err("Environment light needs at least one sample");
+        }
+        self.samples = samples;
+        Ok(())
+    }
+
+    /// Returns a direction picked in proportion to the luminance, with its
+    /// probability density over solid angle.
+    pub fn sample_direction(&self, u1: f32, u2: f32) -> (Vector, f32) {
+        let (y, dv) = sampling::sample_cdf(&self.rows, u1);
+        let (x, du) = sampling::sample_cdf(&self.columns[y], u2);
+        let u = (x as f32 + du) / self.map.canvas.width as f32;
+        let v = (y as f32 + dv) / self.map.canvas.height as f32;
+        let direction = EnvironmentMap::uv_to_direction(u, v);
+        (direction, self.pdf(direction))
+    }
+
+    /// Probability density of `sample_direction` picking `direction`.
+    pub fn pdf(&self, direction: Vector) -> f32 {
+        let (x, y) = self.map.pixel_for(direction);
+        let bin = |cdf: &[f32], i: usize| cdf[i] - if i == 0 { 0.0 } else { cdf[i - 1] };
+        let p = bin(&self.rows, y) * bin(&self.columns[y], x);
+        // Each pixel spans 2 pi / width by pi / height radians, scaled by
+        // the sine of the angle from the pole.
+        let d = direction.normalized();
+        let sin = (d.x * d.x + d.z * d.z).sqrt().max(f32::MIN_POSITIVE);
+        let (width, height) = (self.map.canvas.width, self.map.canvas.height);
+        p * (width * height) as f32 / (2.0 * PI * PI * sin)
+    }
+
+    fn light_sample(&self, direction: Vector) -> LightSample {
+        LightSample {
+            direction,
+            distance: f32::INFINITY,
+            intensity: self.map.color_for(direction),
+        }
+    }
+}
+
+impl Light for EnvironmentLight {
+    fn intensity(&self) -> Color {
+        self.average
+    }
+
+    fn samples_at(&self, _point: Point, rng: &mut Rng) -> Vec<LightSample> {
+        (0..self.samples)
+            .map(|i| {
+                let u1 = (i as f32 + rng.next_f32()) / self.samples as f32;
+                let (direction, pdf) = self.sample_direction(u1, rng.next_f32());
+                let sample = self.light_sample(direction);
+                LightSample {
+                    intensity: (1.0 / (PI * pdf)) * sample.intensity,
+                    ..sample
+                }
+            })
+            .collect()
+    }
+
+    fn sample(&self, _point: Point, u1: f32, u2: f32) -> (LightSample, Option<f32>) {
+        let (direction, pdf) = self.sample_direction(u1, u2);
+        (self.light_sample(direction), Some(pdf))
+    }
+
+    fn hit(&self, _point: Point, direction: Vector) -> Option<(LightSample, f32)> {
+        Some((self.light_sample(direction), self.pdf(direction)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::point::point;
+    use crate::test_utils::*;
+    use crate::vector::vector;
+    use std::f32::consts::{FRAC_1_SQRT_2, PI, SQRT_2};
 
     #[test]
     fn test_create_point_light() {
@@ -29,4 +519,347 @@ mod tests {
         assert_eq!(light.position, position);
         assert_eq!(light.intensity, intensity);
     }
+
+    #[test]
+    fn test_create_area_light() {
+        let light = AreaLight::new(
+            point(0, 0, 0),
+            vector(2, 0, 0),
+            4,
+            vector(0, 0, 1),
+            2,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        assert_eq!(light.corner, point(0, 0, 0));
+        assert_eq!(light.uvec, vector(0.5, 0.0, 0.0));
+        assert_eq!(light.usteps, 4);
+        assert_eq!(light.vvec, vector(0.0, 0.0, 0.5));
+        assert_eq!(light.vsteps, 2);
+        assert_eq!(light.samples(&mut Rng::new(0)).len(), 8);
+        assert_eq!(light.position(), point(1.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn test_area_light_needs_cells() {
+        let white = Color::new(1., 1., 1.);
+        let corner = point(0, 0, 0);
+        let (u, v) = (vector(2, 0, 0), vector(0, 0, 1));
+        assert!(AreaLight::new(corner, u, 0, v, 2, white).is_err());
+        assert!(AreaLight::new(corner, u, 4, v, 0, white).is_err());
+        assert!(AreaLight::with_radiance(corner, u, 0, v, 0, white).is_err());
+    }
+
+    #[test]
+    fn test_find_single_point_on_area_light() {
+        let mut light = AreaLight::new(
+            point(0, 0, 0),
+            vector(2, 0, 0),
+            4,
+            vector(0, 0, 1),
+            2,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        light.jitter = false;
+        let cases = [
+            (0, 0, point(0.25, 0.0, 0.25)),
+            (1, 0, point(0.75, 0.0, 0.25)),
+            (0, 1, point(0.25, 0.0, 0.75)),
+            (2, 0, point(1.25, 0.0, 0.25)),
+            (3, 1, point(1.75, 0.0, 0.75)),
+        ];
+        for (u, v, p) in cases {
+            assert_eq!(light.point_on_light(u, v, &mut Rng::new(0)), p);
+        }
+    }
+
+    #[test]
+    fn test_jittered_point_stays_in_cell() {
+        let light = AreaLight::new(
+            point(0, 0, 0),
+            vector(2, 0, 0),
+            4,
+            vector(0, 0, 1),
+            2,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        let mut rng = Rng::new(0);
+        for _ in 0..100 {
+            let p = light.point_on_light(3, 1, &mut rng);
+            assert!((1.5..=2.0).contains(&p.x));
+            assert_eq!(p.y, 0.0);
+            assert!((0.5..=1.0).contains(&p.z));
+        }
+    }
+
+    #[test]
+    fn test_jittered_samples_follow_the_seed() {
+        let light = AreaLight::new(
+            point(0, 0, 0),
+            vector(2, 0, 0),
+            4,
+            vector(0, 0, 1),
+            2,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        let p = point(0, 5, 0);
+        let samples = light.samples_at(p, &mut Rng::new(3));
+        assert_eq!(samples, light.samples_at(p, &mut Rng::new(3)));
+        assert_ne!(samples, light.samples_at(p, &mut Rng::new(4)));
+    }
+
+    #[test]
+    fn test_point_light_has_single_sample() {
+        let light = PointLight::new(point(0, 0, 5), Color::new(1., 1., 1.));
+        let samples = light.samples_at(point(0, 0, 1), &mut Rng::new(0));
+        assert_eq!(
+            samples,
+            vec![LightSample {
+                direction: vector(0, 0, 1),
+                distance: 4.0,
+                intensity: Color::new(1., 1., 1.),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_area_light_samples_every_cell() {
+        let mut light = AreaLight::new(
+            point(-1, 0, 5),
+            vector(2, 0, 0),
+            2,
+            vector(0, 0, 1),
+            1,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        light.jitter = false;
+        let samples = light.samples_at(point(0, 0, 0), &mut Rng::new(0));
+        assert_eq!(samples.len(), 2);
+        assert_near!(samples[0].direction, vector(-0.5, 0.0, 5.5).normalized());
+        assert_near!(samples[1].direction, vector(0.5, 0.0, 5.5).normalized());
+        assert_near!(samples[0].distance, 30.5f32.sqrt());
+    }
+
+    #[test]
+    fn test_spot_light_falloff() {
+        let light = SpotLight::new(
+            point(0, 0, 0),
+            vector(0, -1, 0),
+            PI / 6.0,
+            PI / 4.0,
+            Color::new(1., 1., 1.),
+        );
+        // Points at 0, 20, 30, 37.5, 45 and 60 degrees from the axis.
+        let at = |degrees: f32| {
+            let a = degrees.to_radians();
+            point(a.sin(), -a.cos(), 0.0)
+        };
+        assert_eq!(light.falloff(at(0.0)), 1.0);
+        assert_eq!(light.falloff(at(20.0)), 1.0);
+        assert_near!(light.falloff(at(30.0)), 1.0, 1e-4);
+        assert!((0.4..0.6).contains(&light.falloff(at(37.5))));
+        assert_near!(light.falloff(at(45.0)), 0.0, 1e-4);
+        assert_eq!(light.falloff(at(60.0)), 0.0);
+        assert_eq!(light.falloff(point(0, 1, 0)), 0.0);
+    }
+
+    #[test]
+    fn test_spot_light_sample_is_dimmed_by_falloff() {
+        let light = SpotLight::new(
+            point(0, 10, 0),
+            vector(0, -1, 0),
+            PI / 6.0,
+            PI / 4.0,
+            Color::new(1., 1., 1.),
+        );
+        let inside = light.samples_at(point(0, 0, 0), &mut Rng::new(0));
+        assert_eq!(inside[0].direction, vector(0, 1, 0));
+        assert_eq!(inside[0].distance, 10.0);
+        assert_eq!(inside[0].intensity, Color::new(1., 1., 1.));
+        let outside = light.samples_at(point(20, 0, 0), &mut Rng::new(0));
+        assert_eq!(outside[0].intensity, Color::black());
+    }
+
+    #[test]
+    fn test_directional_light_is_infinitely_far() {
+        let light = DirectionalLight::new(vector(0, -2, 0), Color::new(1., 1., 1.));
+        for p in [point(0, 0, 0), point(100, -50, 3)] {
+            let samples = light.samples_at(p, &mut Rng::new(0));
+            assert_eq!(samples.len(), 1);
+            assert_eq!(samples[0].direction, vector(0, 1, 0));
+            assert_eq!(samples[0].distance, f32::INFINITY);
+        }
+    }
+
+    #[test]
+    fn test_point_light_sample_is_delta() {
+        let light = PointLight::new(point(0, 0, 5), Color::new(1., 1., 1.));
+        let (sample, pdf) = light.sample(point(0, 0, 1), 0.3, 0.7);
+        assert_eq!(
+            sample,
+            light.samples_at(point(0, 0, 1), &mut Rng::new(0))[0]
+        );
+        assert_eq!(pdf, None);
+        assert_eq!(light.hit(point(0, 0, 1), vector(0, 0, 1)), None);
+    }
+
+    #[test]
+    fn test_area_light_sample_density() {
+        let light = AreaLight::with_radiance(
+            point(-1, 4, -1),
+            vector(2, 0, 0),
+            2,
+            vector(0, 0, 2),
+            2,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        // Straight below the center, the density is d^2 / (area * cos).
+        let (sample, pdf) = light.sample(point(0, 0, 0), 0.5, 0.5);
+        assert_near!(sample.direction, vector(0, 1, 0));
+        assert_near!(sample.distance, 4.0);
+        assert_near!(pdf.unwrap(), 16.0 / 4.0);
+        // From the corner, at 45 degrees.
+        let (sample, pdf) = light.sample(point(-1, 0, 3), 0.0, 0.0);
+        assert_near!(sample.distance, 4.0 * SQRT_2);
+        assert_near!(pdf.unwrap(), 32.0 / (4.0 * FRAC_1_SQRT_2), 1e-4);
+    }
+
+    #[test]
+    fn test_ray_hits_area_light() {
+        let light = AreaLight::with_radiance(
+            point(-1, 4, -1),
+            vector(2, 0, 0),
+            2,
+            vector(0, 0, 2),
+            2,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        let p = point(0.5, 0.0, 0.0);
+        let (sample, pdf) = light.sample(p, 0.2, 0.9);
+        let (hit, hit_pdf) = light.hit(p, sample.direction).unwrap();
+        assert_near!(hit.distance, sample.distance, 1e-4);
+        assert_near!(hit_pdf, pdf.unwrap(), 1e-3);
+        assert_eq!(light.hit(p, vector(0, -1, 0)), None);
+        assert_eq!(light.hit(p, vector(1, 1, 0).normalized()), None);
+        assert_eq!(light.hit(p, vector(1, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_area_light_without_radiance_is_made_of_points() {
+        let light = AreaLight::new(
+            point(-1, 4, -1),
+            vector(2, 0, 0),
+            2,
+            vector(0, 0, 2),
+            2,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        let (sample, pdf) = light.sample(point(0, 0, 0), 0.5, 0.5);
+        assert_eq!(sample.intensity, Color::new(1., 1., 1.));
+        assert_eq!(pdf, None);
+        assert_eq!(light.hit(point(0, 0, 0), vector(0, 1, 0)), None);
+    }
+
+    #[test]
+    fn test_area_light_samples_match_radiance() {
+        let mut light = AreaLight::with_radiance(
+            point(-1, 4, -1),
+            vector(2, 0, 0),
+            1,
+            vector(0, 0, 2),
+            1,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        light.jitter = false;
+        assert_eq!(light.intensity(), Color::black());
+        // The irradiance is about radiance * area / d^2, which phong turns
+        // into pi times the intensity.
+        let samples = light.samples_at(point(0, 0, 0), &mut Rng::new(0));
+        assert_eq!(samples.len(), 1);
+        assert_near!(
+            samples[0].intensity,
+            Color::new(1., 1., 1.) * (4.0 / (16.0 * PI))
+        );
+    }
+
+    fn sky(width: usize, height: usize, c: Color) -> EnvironmentMap {
+        EnvironmentMap::new(crate::canvas::Canvas::new(width, height, c)).unwrap()
+    }
+
+    #[test]
+    fn test_environment_light_needs_samples() {
+        let mut light = EnvironmentLight::new(sky(4, 2, Color::white()));
+        assert_eq!(light.samples(), 16);
+        assert!(light.set_samples(0).is_err());
+        assert_eq!(light.samples(), 16);
+        light.set_samples(3).unwrap();
+        assert_eq!(light.samples_at(point(0, 0, 0), &mut Rng::new(0)).len(), 3);
+    }
+
+    #[test]
+    fn test_uniform_environment_light() {
+        let light = EnvironmentLight::new(sky(64, 32, Color::white()));
+        assert_near!(light.intensity(), Color::white(), 1e-3);
+        let (sample, pdf) = light.hit(point(1, 2, 3), vector(1, 0, 0)).unwrap();
+        assert_eq!(sample.intensity, Color::white());
+        assert_eq!(sample.distance, f32::INFINITY);
+        // Uniform over the sphere.
+        assert_near!(pdf, 1.0 / (4.0 * PI), 1e-3);
+        let (sample, pdf) = light.sample_direction(0.3, 0.7);
+        assert_near!(sample.len(), 1.0);
+        assert_near!(pdf, light.pdf(sample));
+    }
+
+    #[test]
+    fn test_environment_light_samples_bright_pixels() {
+        let mut map = sky(8, 4, Color::black());
+        map.canvas.set(5, 1, Color::new(10.0, 10.0, 10.0));
+        let light = EnvironmentLight::new(map);
+        for (u1, u2) in [(0.0, 0.0), (0.3, 0.9), (0.99, 0.5)] {
+            let (sample, pdf) = light.sample(point(0, 0, 0), u1, u2);
+            assert_eq!(light.map.pixel_for(sample.direction), (5, 1));
+            assert_eq!(sample.intensity, Color::new(10.0, 10.0, 10.0));
+            assert!(pdf.unwrap() > 0.0);
+        }
+        assert_eq!(light.pdf(vector(0, -1, 0)), 0.0);
+    }
+
+    #[test]
+    fn test_environment_light_estimates_irradiance() {
+        // A white sky over a black ground gives an irradiance of pi to a
+        // surface facing up.
+        let mut map = sky(32, 16, Color::white());
+        for y in 8..16 {
+            for x in 0..32 {
+                map.canvas.set(x, y, Color::black());
+            }
+        }
+        let mut light = EnvironmentLight::new(map);
+        let n = vector(0, 1, 0);
+        let count = 4000;
+        let mut rng = crate::random::Rng::new(0);
+        let mut sum = 0.0;
+        for _ in 0..count {
+            let (sample, pdf) = light.sample(point(0, 0, 0), rng.next_f32(), rng.next_f32());
+            sum += dot(sample.direction, n).max(0.0) * sample.intensity.red / pdf.unwrap();
+        }
+        assert_near!(sum / count as f32, PI, 0.1);
+
+        // Samples for `lighting` are scaled by 1 / pi.
+        light.set_samples(count).unwrap();
+        let samples = light.samples_at(point(0, 0, 0), &mut Rng::new(0));
+        let sum: f32 = samples
+            .iter()
+            .map(|s| dot(s.direction, n).max(0.0) * s.intensity.red)
+            .sum();
+        assert_near!(sum / count as f32, 1.0, 2e-2);
+    }
 }
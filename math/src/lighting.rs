@@ -1,43 +1,205 @@
 use crate::color::Color;
-use crate::light::PointLight;
+use crate::light::{Light, LightSample};
 use crate::material::Material;
-use crate::point::Point;
 use crate::vector::{dot, Vector};
+use std::f32::consts::PI;
 
-pub fn phong(m: Material, l: &PointLight, pos: Point, v: Vector, n: Vector) -> Color {
-    let i = m.color * l.intensity;
+/// The ambient term of `l`, plus `f` averaged over `samples`. Shared by the
+/// shading functions below, which only differ in the light they reflect
+/// from each sample.
+fn shade_samples(
+    m: &Material,
+    l: &dyn Light,
+    samples: &[LightSample],
+    f: impl Fn(&LightSample) -> Color,
+) -> Color {
+    let ambient_lighting = m.ambient * (m.color * l.intensity());
+    if samples.is_empty() {
+        return ambient_lighting;
+    }
+    let sum = samples
+        .iter()
+        .fold(Color::black(), |sum, sample| sum + f(sample));
+    ambient_lighting + (1.0 / samples.len() as f32) * sum
+}
 
-    let lm = (l.position - pos).normalized();
-    let rm = (-lm).reflected(n);
+/// Phong shading of the light from `samples`, the samples of `l` at the
+/// point with those in shadow turned black, as returned by
+/// `World::light_samples`.
+pub fn phong(m: &Material, l: &dyn Light, samples: &[LightSample], v: Vector, n: Vector) -> Color {
+    shade_samples(m, l, samples, |sample| {
+        let lm = sample.direction;
+        let rm = (-lm).reflected(n);
 
-    let ambient_lighting = m.ambient * i;
+        let diffuse_lighting = {
+            let x = dot(lm, n);
+            if x > 0.0 {
+                m.diffuse * x * (m.color * sample.intensity)
+            } else {
+                Color::black()
+            }
+        };
 
-    let diffuse_lighting = {
-        let x = dot(lm, n);
-        if x > 0.0 {
-            m.diffuse * x * i
-        } else {
-            Color::black()
+        let specular_lighting = {
+            let x = dot(rm, v);
+            if x > 0.0 {
+                m.specular * x.powf(m.shininess) * sample.intensity
+            } else {
+                Color::black()
+            }
+        };
+
+        diffuse_lighting + specular_lighting
+    })
+}
+
+/// Like `phong`, but with the specular highlight computed from the half
+/// vector between the light and the eye, which keeps highlights round at
+/// grazing angles. Needs about four times the shininess of `phong` for the
+/// same highlight size.
+pub fn blinn_phong(
+    m: &Material,
+    l: &dyn Light,
+    samples: &[LightSample],
+    v: Vector,
+    n: Vector,
+) -> Color {
+    shade_samples(m, l, samples, |sample| {
+        let n_dot_l = dot(sample.direction, n);
+        if n_dot_l <= 0.0 {
+            return Color::black();
         }
-    };
+        let h = (sample.direction + v).normalized();
+        let diffuse_lighting = m.diffuse * n_dot_l * (m.color * sample.intensity);
+        let specular_lighting =
+            m.specular * dot(h, n).max(0.0).powf(m.shininess) * sample.intensity;
+        diffuse_lighting + specular_lighting
+    })
+}
+
+/// Purely diffuse shading, `phong` without the specular highlight.
+pub fn lambert(
+    m: &Material,
+    l: &dyn Light,
+    samples: &[LightSample],
+    _v: Vector,
+    n: Vector,
+) -> Color {
+    shade_samples(m, l, samples, |sample| {
+        let n_dot_l = dot(sample.direction, n).max(0.0);
+        m.diffuse * n_dot_l * (m.color * sample.intensity)
+    })
+}
 
-    let specular_lighting = {
-        let x = dot(rm, v);
-        if x > 0.0 {
-            m.specular * x.powf(m.shininess) * l.intensity
+/// Cel shading: the diffuse term is rounded up to one of `bands` flat
+/// levels, and the Blinn-Phong highlight is either fully on or off.
+pub fn toon(
+    m: &Material,
+    l: &dyn Light,
+    samples: &[LightSample],
+    v: Vector,
+    n: Vector,
+    bands: usize,
+) -> Color {
+    let bands = bands.max(1) as f32;
+    shade_samples(m, l, samples, |sample| {
+        let n_dot_l = dot(sample.direction, n);
+        if n_dot_l <= 0.0 {
+            return Color::black();
+        }
+        let level = (n_dot_l * bands).ceil() / bands;
+        let diffuse_lighting = m.diffuse * level * (m.color * sample.intensity);
+        let h = (sample.direction + v).normalized();
+        let specular_lighting = if dot(h, n).max(0.0).powf(m.shininess) > 0.5 {
+            m.specular * sample.intensity
         } else {
             Color::black()
+        };
+        diffuse_lighting + specular_lighting
+    })
+}
+
+/// Reflectance of dielectrics at normal incidence, which is about 4% for
+/// most of them.
+const DIELECTRIC_F0: f32 = 0.04;
+
+/// GGX (Trowbridge-Reitz) distribution of microfacet normals, `alpha` being
+/// the squared perceptual roughness.
+fn ggx_distribution(n_dot_h: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+/// Smith's shadowing-masking term, with the Schlick-GGX approximation for
+/// each direction.
+fn smith_geometry(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let g1 = |x: f32| x / (x * (1.0 - k) + k);
+    g1(n_dot_v) * g1(n_dot_l)
+}
+
+/// Schlick's approximation of the Fresnel reflectance, `f0` being the
+/// reflectance at normal incidence.
+fn schlick_fresnel(cos: f32, f0: Color) -> Color {
+    let f = (1.0 - cos).powi(5);
+    f0 + f * (Color::white() - f0)
+}
+
+/// Metallic/roughness shading with the Cook-Torrance microfacet model.
+///
+/// Unlike `phong`, the diffuse term is divided by pi so that a surface
+/// never reflects more light than it receives, so lights need to be about
+/// three times as bright for the same exposure. `samples` are the same as
+/// for `phong`.
+pub fn cook_torrance(
+    m: &Material,
+    l: &dyn Light,
+    samples: &[LightSample],
+    v: Vector,
+    n: Vector,
+) -> Color {
+    shade_samples(m, l, samples, |sample| {
+        let n_dot_l = dot(n, sample.direction);
+        if n_dot_l <= 0.0 {
+            return Color::black();
         }
-    };
+        let brdf = cook_torrance_brdf(m, sample.direction, v, n);
+        n_dot_l * (brdf * sample.intensity)
+    })
+}
+
+/// The Cook-Torrance BRDF for light arriving from `l` and leaving towards
+/// `v`, both unit vectors pointing away from the surface.
+pub fn cook_torrance_brdf(m: &Material, l: Vector, v: Vector, n: Vector) -> Color {
+    let n_dot_l = dot(n, l);
+    if n_dot_l <= 0.0 {
+        return Color::black();
+    }
+    let roughness = m.roughness.clamp(0.01, 1.0);
+    let alpha = roughness * roughness;
+    let f0 = (1.0 - m.metallic) * Color::new(DIELECTRIC_F0, DIELECTRIC_F0, DIELECTRIC_F0)
+        + m.metallic * m.color;
+    let n_dot_v = dot(n, v).max(1e-4);
+    let h = (l + v).normalized();
+    let n_dot_h = dot(n, h).max(0.0);
+    let v_dot_h = dot(v, h).max(0.0);
 
-    ambient_lighting + diffuse_lighting + specular_lighting
+    let f = schlick_fresnel(v_dot_h, f0);
+    let d = ggx_distribution(n_dot_h, alpha);
+    let g = smith_geometry(n_dot_v, n_dot_l, roughness);
+    let specular = (d * g / (4.0 * n_dot_v * n_dot_l)) * f;
+    let diffuse = ((1.0 - m.metallic) / PI) * ((Color::white() - f) * m.color);
+    diffuse + specular
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::light::{AreaLight, PointLight};
     use crate::material::Material;
-    use crate::point::point;
+    use crate::point::{point, Point};
+    use crate::random::Rng;
     use crate::test_utils::*;
     use crate::vector::vector;
     use std::f32::consts::FRAC_1_SQRT_2;
@@ -49,7 +211,13 @@ mod tests {
         let eye_vec = vector(0, 0, -1);
         let normal_vec = vector(0, 0, -1);
         let light = PointLight::new(point(0, 0, -10), Color::new(1., 1., 1.));
-        let result = phong(m, &light, pos, eye_vec, normal_vec);
+        let result = phong(
+            &m,
+            &light,
+            &light.samples_at(pos, &mut Rng::new(0)),
+            eye_vec,
+            normal_vec,
+        );
         assert_eq!(result, Color::new(1.9, 1.9, 1.9));
     }
 
@@ -60,7 +228,13 @@ mod tests {
         let eye_vec = vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
         let normal_vec = vector(0, 0, -1);
         let light = PointLight::new(point(0, 0, -10), Color::new(1., 1., 1.));
-        let result = phong(m, &light, pos, eye_vec, normal_vec);
+        let result = phong(
+            &m,
+            &light,
+            &light.samples_at(pos, &mut Rng::new(0)),
+            eye_vec,
+            normal_vec,
+        );
         assert_eq!(result, Color::new(1.0, 1.0, 1.0));
     }
 
@@ -71,7 +245,13 @@ mod tests {
         let eye_vec = vector(0, 0, -1);
         let normal_vec = vector(0, 0, -1);
         let light = PointLight::new(point(0, 10, -10), Color::new(1., 1., 1.));
-        let result = phong(m, &light, pos, eye_vec, normal_vec);
+        let result = phong(
+            &m,
+            &light,
+            &light.samples_at(pos, &mut Rng::new(0)),
+            eye_vec,
+            normal_vec,
+        );
         assert_near!(result, Color::new(0.7364, 0.7364, 0.7364));
     }
 
@@ -82,7 +262,13 @@ mod tests {
         let eye_vec = vector(0.0, -FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
         let normal_vec = vector(0, 0, -1);
         let light = PointLight::new(point(0, 10, -10), Color::new(1., 1., 1.));
-        let result = phong(m, &light, pos, eye_vec, normal_vec);
+        let result = phong(
+            &m,
+            &light,
+            &light.samples_at(pos, &mut Rng::new(0)),
+            eye_vec,
+            normal_vec,
+        );
         assert_near!(result, Color::new(1.6364, 1.6364, 1.6364));
     }
 
@@ -93,7 +279,311 @@ mod tests {
         let eye_vec = vector(0, 0, -1);
         let normal_vec = vector(0, 0, -1);
         let light = PointLight::new(point(0, 0, 10), Color::new(1., 1., 1.));
-        let result = phong(m, &light, pos, eye_vec, normal_vec);
+        let result = phong(
+            &m,
+            &light,
+            &light.samples_at(pos, &mut Rng::new(0)),
+            eye_vec,
+            normal_vec,
+        );
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    /// Turns the samples for which `hidden` is true black, like
+    /// `World::light_samples` does for those in shadow.
+    fn shadowed(
+        mut samples: Vec<LightSample>,
+        hidden: impl Fn(&LightSample) -> bool,
+    ) -> Vec<LightSample> {
+        for sample in &mut samples {
+            if hidden(sample) {
+                sample.intensity = Color::black();
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn test_lighting_with_surface_in_shadow() {
+        let m = Material::new();
+        let pos = point(0, 0, 0);
+        let eye_vec = vector(0, 0, -1);
+        let normal_vec = vector(0, 0, -1);
+        let light = PointLight::new(point(0, 0, -10), Color::new(1., 1., 1.));
+        let samples = shadowed(light.samples_at(pos, &mut Rng::new(0)), |_| true);
+        let result = phong(&m, &light, &samples, eye_vec, normal_vec);
         assert_eq!(result, Color::new(0.1, 0.1, 0.1));
     }
+
+    #[derive(Debug)]
+    struct NoSamples;
+
+    impl Light for NoSamples {
+        fn intensity(&self) -> Color {
+            Color::new(1., 1., 1.)
+        }
+
+        fn samples_at(&self, _point: Point, _rng: &mut Rng) -> Vec<LightSample> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn test_lighting_with_light_without_samples() {
+        let m = Material::new();
+        let pos = point(0, 0, 0);
+        let samples = NoSamples.samples_at(pos, &mut Rng::new(0));
+        let result = phong(&m, &NoSamples, &samples, vector(0, 0, -1), vector(0, 0, -1));
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+        let (sample, pdf) = NoSamples.sample(pos, 0.5, 0.5);
+        assert_eq!(sample.intensity, Color::black());
+        assert_eq!(pdf, None);
+    }
+
+    #[test]
+    fn test_lighting_with_partly_shadowed_area_light() {
+        let mut m = Material::new();
+        m.ambient = 0.0;
+        let pos = point(0, 0, 0);
+        let eye_vec = vector(0, 0, -1);
+        let normal_vec = vector(0, 0, -1);
+        let mut light = AreaLight::new(
+            point(-1, -1, -10),
+            vector(2, 0, 0),
+            2,
+            vector(0, 2, 0),
+            1,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        light.jitter = false;
+        let samples = light.samples_at(pos, &mut Rng::new(0));
+        let left = shadowed(samples.clone(), |s| s.direction.x > 0.0);
+        let right = shadowed(samples.clone(), |s| s.direction.x < 0.0);
+        let full = phong(&m, &light, &samples, eye_vec, normal_vec);
+        let half = phong(&m, &light, &left, eye_vec, normal_vec);
+        assert_near!(half, full * 0.5);
+        assert_near!(half + phong(&m, &light, &right, eye_vec, normal_vec), full);
+    }
+
+    fn cook_torrance_material(color: Color, metallic: f32, roughness: f32) -> Material {
+        let mut m = Material::new();
+        m.color = color;
+        m.metallic = metallic;
+        m.roughness = roughness;
+        m
+    }
+
+    #[test]
+    fn test_ggx_distribution_reference_values() {
+        // Peak of the distribution is 1 / (pi * alpha^2).
+        assert_near!(ggx_distribution(1.0, 0.25), 5.09296, 1e-4);
+        assert_near!(ggx_distribution(1.0, 1.0), 1.0 / PI);
+        // With alpha = 1 the distribution is uniform.
+        assert_near!(ggx_distribution(0.5, 1.0), 1.0 / PI);
+        assert_near!(ggx_distribution(FRAC_1_SQRT_2, 0.5), 0.20372, 1e-4);
+    }
+
+    #[test]
+    fn test_smith_geometry_reference_values() {
+        assert_near!(smith_geometry(1.0, 1.0, 0.5), 1.0);
+        // k = 0.28125 for roughness 0.5.
+        assert_near!(smith_geometry(0.5, 1.0, 0.5), 0.78049, 1e-4);
+        assert_near!(smith_geometry(0.5, 0.5, 0.5), 0.60916, 1e-4);
+        assert_near!(smith_geometry(0.0, 1.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn test_schlick_fresnel_reference_values() {
+        let f0 = Color::new(0.04, 0.04, 0.04);
+        assert_near!(schlick_fresnel(1.0, f0), f0);
+        assert_near!(schlick_fresnel(0.0, f0), Color::white());
+        assert_near!(schlick_fresnel(0.5, f0), Color::new(0.07, 0.07, 0.07), 1e-4);
+    }
+
+    #[test]
+    fn test_cook_torrance_with_eye_and_light_along_normal() {
+        let m = cook_torrance_material(Color::white(), 0.0, 1.0);
+        let light = PointLight::new(point(0, 0, -10), Color::white());
+        let result = cook_torrance(
+            &m,
+            &light,
+            &light.samples_at(point(0, 0, 0), &mut Rng::new(0)),
+            vector(0, 0, -1),
+            vector(0, 0, -1),
+        );
+        // ambient + (1 - F0) / pi + D * G * F0 / 4, with D = 1 / pi and G = 1.
+        let expected = 0.1 + 0.96 / PI + 0.04 / (4.0 * PI);
+        assert_near!(result, Color::new(expected, expected, expected));
+    }
+
+    #[test]
+    fn test_cook_torrance_metal_has_no_diffuse() {
+        let gold = Color::new(1.0, 0.766, 0.336);
+        let m = cook_torrance_material(gold, 1.0, 1.0);
+        let light = PointLight::new(point(0, 0, -10), Color::white());
+        let result = cook_torrance(
+            &m,
+            &light,
+            &light.samples_at(point(0, 0, 0), &mut Rng::new(0)),
+            vector(0, 0, -1),
+            vector(0, 0, -1),
+        );
+        // Only the specular term, tinted by the metal's color.
+        assert_near!(result, 0.1 * gold + (1.0 / (4.0 * PI)) * gold);
+    }
+
+    #[test]
+    fn test_cook_torrance_with_light_behind_surface() {
+        let m = cook_torrance_material(Color::white(), 0.0, 0.5);
+        let light = PointLight::new(point(0, 0, 10), Color::white());
+        let result = cook_torrance(
+            &m,
+            &light,
+            &light.samples_at(point(0, 0, 0), &mut Rng::new(0)),
+            vector(0, 0, -1),
+            vector(0, 0, -1),
+        );
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn test_cook_torrance_conserves_energy() {
+        // A white surface lit head on reflects almost all, but never more,
+        // of the light it receives.
+        let light = PointLight::new(point(0, 0, -10), Color::white());
+        for roughness in [0.1, 0.3, 0.6, 1.0] {
+            let mut m = cook_torrance_material(Color::white(), 0.0, roughness);
+            m.ambient = 0.0;
+            let mut total = 0.0;
+            let steps = 2000;
+            for i in 0..steps {
+                // Integrates over a half circle of eye directions, weighted
+                // like a 2D slice of the hemisphere.
+                let theta = (i as f32 + 0.5) / steps as f32 * PI / 2.0;
+                let eye_vec = vector(theta.sin(), 0.0, -theta.cos());
+                let c = cook_torrance(
+                    &m,
+                    &light,
+                    &light.samples_at(point(0, 0, 0), &mut Rng::new(0)),
+                    eye_vec,
+                    vector(0, 0, -1),
+                );
+                total += c.red * theta.cos() * theta.sin() * (PI / 2.0 / steps as f32);
+            }
+            let albedo = 2.0 * PI * total;
+            assert!((0.95..=1.0).contains(&albedo), "{}: {}", roughness, albedo);
+        }
+    }
+
+    #[test]
+    fn test_blinn_phong_highlight_peaks_at_half_vector() {
+        let mut m = Material::new();
+        m.shininess = 50.0;
+        let light = PointLight::new(point(0, 0, -10), Color::white());
+        let normal_vec = vector(0, 0, -1);
+        let result = blinn_phong(
+            &m,
+            &light,
+            &light.samples_at(point(0, 0, 0), &mut Rng::new(0)),
+            vector(0, 0, -1),
+            normal_vec,
+        );
+        assert_near!(result, Color::new(1.9, 1.9, 1.9));
+        // Eye 45 degrees off the normal puts the half vector 22.5 degrees off.
+        let eye_vec = vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
+        let result = blinn_phong(
+            &m,
+            &light,
+            &light.samples_at(point(0, 0, 0), &mut Rng::new(0)),
+            eye_vec,
+            normal_vec,
+        );
+        let specular = 0.9 * (PI / 8.0).cos().powf(50.0);
+        assert_near!(
+            result,
+            Color::new(1.0 + specular, 1.0 + specular, 1.0 + specular)
+        );
+    }
+
+    #[test]
+    fn test_lambert_has_no_highlight() {
+        let m = Material::new();
+        let light = PointLight::new(point(0, 0, -10), Color::white());
+        let normal_vec = vector(0, 0, -1);
+        let result = lambert(
+            &m,
+            &light,
+            &light.samples_at(point(0, 0, 0), &mut Rng::new(0)),
+            vector(0, 0, -1),
+            normal_vec,
+        );
+        assert_near!(result, Color::new(1.0, 1.0, 1.0));
+        let light = PointLight::new(point(0, 10, -10), Color::white());
+        let result = lambert(
+            &m,
+            &light,
+            &light.samples_at(point(0, 0, 0), &mut Rng::new(0)),
+            vector(0, 0, -1),
+            normal_vec,
+        );
+        let expected = 0.1 + 0.9 * FRAC_1_SQRT_2;
+        assert_near!(result, Color::new(expected, expected, expected));
+    }
+
+    #[test]
+    fn test_toon_quantizes_diffuse() {
+        let mut m = Material::new();
+        m.specular = 0.0;
+        let normal_vec = vector(0, 0, -1);
+        let eye_vec = vector(0, 0, -1);
+        // cos(60 degrees) = 0.5 falls in the second of three bands.
+        let light = PointLight::new(point(0.0, 10.0 * 3f32.sqrt(), -10.0), Color::white());
+        let result = toon(
+            &m,
+            &light,
+            &light.samples_at(point(0, 0, 0), &mut Rng::new(0)),
+            eye_vec,
+            normal_vec,
+            3,
+        );
+        let expected = 0.1 + 0.9 * (2.0 / 3.0);
+        assert_near!(result, Color::new(expected, expected, expected));
+        let light = PointLight::new(point(0, 0, -10), Color::white());
+        let result = toon(
+            &m,
+            &light,
+            &light.samples_at(point(0, 0, 0), &mut Rng::new(0)),
+            eye_vec,
+            normal_vec,
+            3,
+        );
+        assert_near!(result, Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_toon_highlight_is_on_or_off() {
+        let m = Material::new();
+        let light = PointLight::new(point(0, 0, -10), Color::white());
+        let normal_vec = vector(0, 0, -1);
+        let result = toon(
+            &m,
+            &light,
+            &light.samples_at(point(0, 0, 0), &mut Rng::new(0)),
+            vector(0, 0, -1),
+            normal_vec,
+            3,
+        );
+        assert_near!(result, Color::new(1.9, 1.9, 1.9));
+        let eye_vec = vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
+        let result = toon(
+            &m,
+            &light,
+            &light.samples_at(point(0, 0, 0), &mut Rng::new(0)),
+            eye_vec,
+            normal_vec,
+            3,
+        );
+        assert_near!(result, Color::new(1.0, 1.0, 1.0));
+    }
 }
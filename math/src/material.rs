@@ -1,39 +1,111 @@
 use crate::color::Color;
+use crate::pattern::Pattern;
+use crate::shader::{Phong, Shader};
+use std::rc::Rc;
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct Material {
+    /// How the surface reflects the lights of the world. Compared by value.
+    pub shader: Rc<dyn Shader>,
     pub color: Color,
+    /// Replaces `color` when set, evaluated in the object space of the shape.
+    /// Compared by identity.
+    pub pattern: Option<Rc<dyn Pattern>>,
+    /// Radiance the surface glows with, which makes the shape a light source
+    /// for the path tracer.
+    pub emission: Color,
     pub ambient: f32,
     pub diffuse: f32,
     pub specular: f32,
     pub shininess: f32,
+    /// How much of the surrounding scene is mirrored, from 0 to 1.
+    pub reflective: f32,
+    /// How much light passes through the surface, from 0 to 1.
+    pub transparency: f32,
+    pub refractive_index: f32,
+    /// Whether the surface is a metal, from 0 (dielectric) to 1, for the
+    /// `CookTorrance` shader. Metals have no diffuse reflection and tint
+    /// their specular reflection with `color`.
+    pub metallic: f32,
+    /// Perceptual roughness, from 0 (mirror-like) to 1 (matte), for the
+    /// `CookTorrance` shader.
+    pub roughness: f32,
 }
 
 impl Material {
     pub fn new() -> Material {
         let color = Color::new(1., 1., 1.);
         Material {
+            shader: Rc::new(Phong),
             color,
+            pattern: None,
+            emission: Color::black(),
             ambient: 0.1,
             diffuse: 0.9,
             specular: 0.9,
             shininess: 200.0,
+            reflective: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            metallic: 0.0,
+            roughness: 0.5,
         }
     }
 }
 
+/// Shaders are compared by value, so that two `Lambert`s are equal, and
+/// patterns by identity, like shapes. Written out because deriving
+/// `PartialEq` does not compile for `Rc<dyn Shader>` fields.
+impl PartialEq for Material {
+    fn eq(&self, other: &Self) -> bool {
+        *self.shader == *other.shader
+            && self.color == other.color
+            && self.pattern == other.pattern
+            && self.emission == other.emission
+            && self.ambient == other.ambient
+            && self.diffuse == other.diffuse
+            && self.specular == other.specular
+            && self.shininess == other.shininess
+            && self.reflective == other.reflective
+            && self.transparency == other.transparency
+            && self.refractive_index == other.refractive_index
+            && self.metallic == other.metallic
+            && self.roughness == other.roughness
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::color::Color;
+    use crate::pattern::StripePattern;
 
     #[test]
     fn test_default_material() {
         let m = Material::new();
+        assert_eq!(format!("{:?}", m.shader), "Phong");
+        assert_eq!(m, Material::new());
         assert_eq!(m.color, Color::new(1., 1., 1.));
+        assert!(m.pattern.is_none());
+        assert_eq!(m.emission, Color::black());
         assert_eq!(m.ambient, 0.1);
         assert_eq!(m.diffuse, 0.9);
         assert_eq!(m.specular, 0.9);
         assert_eq!(m.shininess, 200.0);
+        assert_eq!(m.reflective, 0.0);
+        assert_eq!(m.transparency, 0.0);
+        assert_eq!(m.refractive_index, 1.0);
+        assert_eq!(m.metallic, 0.0);
+        assert_eq!(m.roughness, 0.5);
+    }
+
+    #[test]
+    fn test_materials_compare_patterns_by_identity() {
+        let mut a = Material::new();
+        a.pattern = Some(Rc::new(StripePattern::new(Color::white(), Color::black())));
+        let mut b = a.clone();
+        assert_eq!(a, b);
+        b.pattern = Some(Rc::new(StripePattern::new(Color::white(), Color::black())));
+        assert_ne!(a, b);
     }
 }
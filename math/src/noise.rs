@@ -0,0 +1,239 @@
+use crate::point::{point, Point};
+
+/// Ken Perlin's reference permutation of 0..256.
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141,
+    128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+/// Directions to the edge midpoints of a cube, used as gradients.
+const GRADIENTS: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+/// The integer part of a coordinate, reduced to the 256 units `hash`
+/// repeats over so that adding to it can't overflow.
+fn lattice(c: f32) -> i32 {
+    (c.floor() as i32) & 255
+}
+
+/// Hashes a lattice point, wrapping every 256 units.
+fn hash(x: i32, y: i32, z: i32) -> usize {
+    let p = |i: i32| PERMUTATION[(i & 255) as usize] as i32;
+    p(x + p(y + p(z))) as usize
+}
+
+fn grad(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+    let g = GRADIENTS[hash % 12];
+    g[0] * x + g[1] * y + g[2] * z
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+/// Improved Perlin noise, in about `[-1, 1]` and zero at integer points.
+pub fn perlin(p: Point) -> f32 {
+    let (xi, yi, zi) = (lattice(p.x), lattice(p.y), lattice(p.z));
+    let (x, y, z) = (p.x - p.x.floor(), p.y - p.y.floor(), p.z - p.z.floor());
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        grad(
+            hash(xi + dx, yi + dy, zi + dz),
+            x - dx as f32,
+            y - dy as f32,
+            z - dz as f32,
+        )
+    };
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+            lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+        ),
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+            lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+        ),
+    )
+}
+
+/// Simplex noise in about `[-1, 1]`, cheaper than `perlin` and without its
+/// axis-aligned artifacts.
+pub fn simplex(p: Point) -> f32 {
+    const F3: f32 = 1.0 / 3.0;
+    const G3: f32 = 1.0 / 6.0;
+
+    // Skews the input to find the simplex cell.
+    let s = (p.x + p.y + p.z) * F3;
+    let (fi, fj, fk) = ((p.x + s).floor(), (p.y + s).floor(), (p.z + s).floor());
+    let t = (fi + fj + fk) * G3;
+    let x0 = p.x - (fi - t);
+    let y0 = p.y - (fj - t);
+    let z0 = p.z - (fk - t);
+    let (i, j, k) = (lattice(fi), lattice(fj), lattice(fk));
+
+    // Finds which of the six tetrahedra of the cell contains the point.
+    let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
+        if y0 >= z0 {
+            (1, 0, 0, 1, 1, 0)
+        } else if x0 >= z0 {
+            (1, 0, 0, 1, 0, 1)
+        } else {
+            (0, 0, 1, 1, 0, 1)
+        }
+    } else if y0 < z0 {
+        (0, 0, 1, 0, 1, 1)
+    } else if x0 < z0 {
+        (0, 1, 0, 0, 1, 1)
+    } else {
+        (0, 1, 0, 1, 1, 0)
+    };
+
+    let corners = [
+        (0, 0, 0, 0.0),
+        (i1, j1, k1, G3),
+        (i2, j2, k2, 2.0 * G3),
+        (1, 1, 1, 3.0 * G3),
+    ];
+    let mut n = 0.0;
+    for (di, dj, dk, offset) in corners {
+        let x = x0 - di as f32 + offset;
+        let y = y0 - dj as f32 + offset;
+        let z = z0 - dk as f32 + offset;
+        let t = 0.6 - x * x - y * y - z * z;
+        if t > 0.0 {
+            let t2 = t * t;
+            n += t2 * t2 * grad(hash(i + di, j + dj, k + dk), x, y, z);
+        }
+    }
+    32.0 * n
+}
+
+fn scaled(p: Point, s: f32) -> Point {
+    point(p.x * s, p.y * s, p.z * s)
+}
+
+/// Fractal Brownian motion: `octaves` layers of Perlin noise, each with
+/// double the frequency and half the amplitude of the previous one.
+pub fn fbm(p: Point, octaves: usize) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    for _ in 0..octaves {
+        sum += amplitude * perlin(scaled(p, frequency));
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum
+}
+
+/// Like `fbm`, but sums the absolute values of the layers, which gives
+/// sharp creases where the noise crosses zero.
+pub fn turbulence(p: Point, octaves: usize) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    for _ in 0..octaves {
+        sum += amplitude * perlin(scaled(p, frequency)).abs();
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples() -> impl Iterator<Item = Point> {
+        (0..1000).map(|i| {
+            let i = i as f32;
+            point(i * 0.137 - 50.0, i * 0.291 - 80.0, i * 0.073 + 3.0)
+        })
+    }
+
+    #[test]
+    fn test_perlin_is_zero_on_lattice() {
+        for (x, y, z) in [(0, 0, 0), (1, 2, 3), (-5, 7, -11)] {
+            assert_eq!(perlin(point(x, y, z)), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_perlin_is_bounded_and_varies() {
+        let values: Vec<f32> = samples().map(perlin).collect();
+        assert!(values.iter().all(|v| (-1.0..=1.0).contains(v)));
+        assert!(values.iter().any(|v| *v > 0.2));
+        assert!(values.iter().any(|v| *v < -0.2));
+    }
+
+    #[test]
+    fn test_perlin_is_continuous() {
+        for p in samples() {
+            let q = point(p.x + 1e-3, p.y, p.z);
+            assert!((perlin(p) - perlin(q)).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_noise_far_from_origin() {
+        for p in [
+            point(2.1e9, -2.1e9, 0.5),
+            point(3.0e9, 1.0e10, -4.0e12),
+            point(f32::MAX, f32::MIN, 0.0),
+        ] {
+            assert!((-1.0..=1.0).contains(&perlin(p)));
+            assert!((-1.0..=1.0).contains(&simplex(p)));
+        }
+    }
+
+    #[test]
+    fn test_simplex_is_bounded_and_varies() {
+        let values: Vec<f32> = samples().map(simplex).collect();
+        assert!(values.iter().all(|v| (-1.0..=1.0).contains(v)));
+        assert!(values.iter().any(|v| *v > 0.2));
+        assert!(values.iter().any(|v| *v < -0.2));
+    }
+
+    #[test]
+    fn test_fbm_with_one_octave_is_perlin() {
+        for p in samples().take(10) {
+            assert_eq!(fbm(p, 1), perlin(p));
+        }
+    }
+
+    #[test]
+    fn test_turbulence_is_non_negative() {
+        assert!(samples().all(|p| turbulence(p, 4) >= 0.0));
+    }
+}
@@ -0,0 +1,400 @@
+use crate::group::Group;
+use crate::point::Point;
+use crate::shape::Shape;
+use crate::triangle::{SmoothTriangle, Triangle};
+use crate::vector::Vector;
+
+/// A corner of a parsed face, as zero-based indices into `ObjFile::vertices`
+/// and `ObjFile::normals`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ObjVertex {
+    pub vertex: usize,
+    pub normal: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ObjGroup {
+    pub name: String,
+    pub triangles: Vec<[ObjVertex; 3]>,
+}
+
+/// Geometry read from a Wavefront OBJ file.
+///
+/// Supports `v`, `vn`, `f` (polygons are fan-triangulated) and `g`
+/// statements; every other line is skipped and counted in `ignored`.
+#[derive(Debug, PartialEq)]
+pub struct ObjFile {
+    pub vertices: Vec<Point>,
+    pub normals: Vec<Vector>,
+    /// Faces before the first `g` statement go to the unnamed group at
+    /// index 0.
+    pub groups: Vec<ObjGroup>,
+    pub ignored: usize,
+}
+
+impl ObjFile {
+    pub fn parse(input: &str) -> Result<ObjFile, String> {
+        let mut obj = ObjFile {
+            vertices: vec![],
+            normals: vec![],
+            groups: vec![ObjGroup {
+                name: String::new(),
+                triangles: vec![],
+            }],
+            ignored: 0,
+        };
+        for (i, line) in input.lines().enumerate() {
+            let parsed = obj
+                .parse_line(line)
+                .map_err(|e| format!("line {}: {}", i + 1, e))?;
+            if !parsed {
+                obj.ignored += 1;
+            }
+        }
+        Ok(obj)
+    }
+
+    pub fn read(filename: &str) -> Result<ObjFile, String> {
+        let input = std::fs::read_to_string(filename).map_err(|e| e.to_string())?;
+        ObjFile::parse(&input)
+    }
+
+    /// Builds triangles for every face of every group.
+    pub fn to_shapes(&self) -> Vec<Box<dyn Shape>> {
+        self.groups
+            .iter()
+            .flat_map(|g| g.triangles.iter())
+            .map(|face| self.make_triangle(face))
+            .collect()
+    }
+
+    /// Builds a group of triangles, with a nested group for each `g`
+    /// statement.
+    pub fn to_group(&self) -> Group {
+        let mut root = Group::new();
+        for (i, obj_group) in self.groups.iter().enumerate() {
+            let triangles = obj_group.triangles.iter().map(|f| self.make_triangle(f));
+            if i == 0 {
+                triangles.for_each(|t| root.add_child(t));
+            } else {
+                let mut g = Group::new();
+                triangles.for_each(|t| g.add_child(t));
+                root.add_child(Box::new(g));
+            }
+        }
+        root
+    }
+
+    fn make_triangle(&self, face: &[ObjVertex; 3]) -> Box<dyn Shape> {
+        let [a, b, c] = face;
+        let (p1, p2, p3) = (
+            self.vertices[a.vertex],
+            self.vertices[b.vertex],
+            self.vertices[c.vertex],
+        );
+        match (a.normal, b.normal, c.normal) {
+            (Some(n1), Some(n2), Some(n3)) => Box::new(SmoothTriangle::new(
+                p1,
+                p2,
+                p3,
+                self.normals[n1],
+                self.normals[n2],
+                self.normals[n3],
+            )),
+            _ => Box::new(Triangle::new(p1, p2, p3)),
+        }
+    }
+
+    /// Returns `Ok(false)` for lines that are not understood.
+    fn parse_line(&mut self, line: &str) -> Result<bool, String> {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => match parse_floats(tokens) {
+                Some([x, y, z]) => self.vertices.push(Point::new(x, y, z)),
+                None => return Ok(false),
+            },
+            Some("vn") => match parse_floats(tokens) {
+                Some([x, y, z]) => self.normals.push(Vector::new(x, y, z)),
+                None => return Ok(false),
+            },
+            Some("f") => {
+                let mut corners = vec![];
+                for token in tokens {
+                    match self.parse_face_vertex(token)? {
+                        Some(v) => corners.push(v),
+                        None => return Ok(false),
+                    }
+                }
+                if corners.len() < 3 {
+                    return Ok(false);
+                }
+                let group = self.groups.last_mut().unwrap();
+                for i in 1..corners.len() - 1 {
+                    group
+                        .triangles
+                        .push([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            Some("g") => {
+                let name = tokens.collect::<Vec<&str>>().join(" ");
+                self.groups.push(ObjGroup {
+                    name,
+                    triangles: vec![],
+                });
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Parses `v`, `v/vt` , `v//vn` or `v/vt/vn` into zero-based indices.
+    /// Negative indices count back from the last vertex or normal read so
+    /// far, `-1` being the last one.
+    fn parse_face_vertex(&self, token: &str) -> Result<Option<ObjVertex>, String> {
+        let mut parts = token.split('/');
+        let vertex = match parts.next().map(str::parse::<isize>) {
+            Some(Ok(i)) => i,
+            _ => return Ok(None),
+        };
+        let normal = match parts.nth(1) {
+            None | Some("") => None,
+            Some(s) => match s.parse::<isize>() {
+                Ok(i) => Some(i),
+                Err(_) => return Ok(None),
+            },
+        };
+        let vertex = resolve_index(vertex, self.vertices.len())
+            .ok_or_else(|| format!("vertex index {} is out of range", vertex))?;
+        let normal = match normal {
+            Some(n) => Some(
+                resolve_index(n, self.normals.len())
+                    .ok_or_else(|| format!("normal index {} is out of range", n))?,
+            ),
+            None => None,
+        };
+        Ok(Some(ObjVertex { vertex, normal }))
+    }
+}
+
+/// Turns a one-based or negative OBJ index into a zero-based index into a
+/// list of `count` elements.
+fn resolve_index(index: isize, count: usize) -> Option<usize> {
+    let i = if index < 0 {
+        count.checked_sub(index.unsigned_abs())?
+    } else {
+        (index as usize).checked_sub(1)?
+    };
+    (i < count).then_some(i)
+}
+
+fn parse_floats<'a>(args: impl Iterator<Item = &'a str>) -> Option<[f32; 3]> {
+    let values: Vec<f32> = args.map(|x| x.parse().ok()).collect::<Option<_>>()?;
+    values.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::point;
+    use crate::ray::Ray;
+    use crate::test_utils::*;
+    use crate::vector::vector;
+
+    fn corner(vertex: usize) -> ObjVertex {
+        ObjVertex {
+            vertex,
+            normal: None,
+        }
+    }
+
+    #[test]
+    fn test_ignore_unrecognized_lines() {
+        let gibberish = "There was a young lady named Bright\n\
+                         who traveled much faster than light.\n\
+                         She set out one day\n\
+                         in a relative way,\n\
+                         and came back the previous night.\n";
+        let obj = ObjFile::parse(gibberish).unwrap();
+        assert_eq!(obj.ignored, 5);
+        assert_eq!(obj.vertices, vec![]);
+    }
+
+    #[test]
+    fn test_vertex_records() {
+        let obj = ObjFile::parse("v -1 1 0\nv -1.0000 0.5000 0.0000\nv 1 0 0\nv 1 1 0\n").unwrap();
+        assert_eq!(
+            obj.vertices,
+            vec![
+                point(-1, 1, 0),
+                point(-1.0, 0.5, 0.0),
+                point(1, 0, 0),
+                point(1, 1, 0)
+            ]
+        );
+        assert_eq!(obj.ignored, 0);
+    }
+
+    #[test]
+    fn test_parse_triangle_faces() {
+        let obj =
+            ObjFile::parse("v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\n\nf 1 2 3\nf 1 3 4\n").unwrap();
+        assert_eq!(
+            obj.groups[0].triangles,
+            vec![
+                [corner(0), corner(1), corner(2)],
+                [corner(0), corner(2), corner(3)]
+            ]
+        );
+        assert_eq!(obj.ignored, 1);
+    }
+
+    #[test]
+    fn test_triangulate_polygons() {
+        let obj = ObjFile::parse("v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nv 0 2 0\n\nf 1 2 3 4 5\n")
+            .unwrap();
+        assert_eq!(
+            obj.groups[0].triangles,
+            vec![
+                [corner(0), corner(1), corner(2)],
+                [corner(0), corner(2), corner(3)],
+                [corner(0), corner(3), corner(4)]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_triangles_in_groups() {
+        let obj = ObjFile::parse(
+            "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\n\
+             g FirstGroup\nf 1 2 3\ng SecondGroup\nf 1 3 4\n",
+        )
+        .unwrap();
+        assert_eq!(obj.groups.len(), 3);
+        assert!(obj.groups[0].triangles.is_empty());
+        assert_eq!(obj.groups[1].name, "FirstGroup");
+        assert_eq!(
+            obj.groups[1].triangles,
+            vec![[corner(0), corner(1), corner(2)]]
+        );
+        assert_eq!(obj.groups[2].name, "SecondGroup");
+        assert_eq!(
+            obj.groups[2].triangles,
+            vec![[corner(0), corner(2), corner(3)]]
+        );
+    }
+
+    #[test]
+    fn test_vertex_normal_records() {
+        let obj = ObjFile::parse("vn 0 0 1\nvn 0.707 0 -0.707\nvn 1 2 3\n").unwrap();
+        assert_eq!(
+            obj.normals,
+            vec![vector(0, 0, 1), vector(0.707, 0.0, -0.707), vector(1, 2, 3)]
+        );
+    }
+
+    #[test]
+    fn test_faces_with_normals() {
+        let obj = ObjFile::parse(
+            "v 0 1 0\nv -1 0 0\nv 1 0 0\n\
+             vn -1 0 0\nvn 1 0 0\nvn 0 1 0\n\
+             f 1//3 2//1 3//2\nf 1/0/3 2/102/1 3/14/2\n",
+        )
+        .unwrap();
+        let face = [
+            ObjVertex {
+                vertex: 0,
+                normal: Some(2),
+            },
+            ObjVertex {
+                vertex: 1,
+                normal: Some(0),
+            },
+            ObjVertex {
+                vertex: 2,
+                normal: Some(1),
+            },
+        ];
+        assert_eq!(obj.groups[0].triangles, vec![face, face]);
+    }
+
+    #[test]
+    fn test_faces_with_negative_indices() {
+        let obj = ObjFile::parse(
+            "v 0 1 0\nv -1 0 0\nv 1 0 0\nvn 0 0 1\n\
+             f -3//-1 -2//-1 -1//-1\n\
+             v 0 2 0\nf 1 -2 -1\n",
+        )
+        .unwrap();
+        let with_normal = |vertex| ObjVertex {
+            vertex,
+            normal: Some(0),
+        };
+        assert_eq!(
+            obj.groups[0].triangles,
+            vec![
+                [with_normal(0), with_normal(1), with_normal(2)],
+                [corner(0), corner(2), corner(3)]
+            ]
+        );
+        assert_eq!(obj.ignored, 0);
+        assert_eq!(
+            ObjFile::parse("v 0 1 0\nf -1 -2 -1\n"),
+            Err("line 2: vertex index -2 is out of range".to_string())
+        );
+    }
+
+    #[test]
+    fn test_face_index_out_of_range() {
+        assert_eq!(
+            ObjFile::parse("v 0 1 0\nv -1 0 0\nf 1 2 3\n"),
+            Err("line 3: vertex index 3 is out of range".to_string())
+        );
+    }
+
+    #[test]
+    fn test_convert_to_shapes() {
+        let obj = ObjFile::parse(
+            "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\n\
+             g FirstGroup\nf 1 2 3\ng SecondGroup\nf 1 3 4\n",
+        )
+        .unwrap();
+        let shapes = obj.to_shapes();
+        assert_eq!(shapes.len(), 2);
+        let r = Ray::new(point(-0.5, 0.25, -2.0), vector(0, 0, 1));
+        assert_eq!(ts(shapes[0].intersect(&r)), vec![2.0]);
+        assert_eq!(ts(shapes[1].intersect(&r)), vec![]);
+    }
+
+    #[test]
+    fn test_convert_faces_with_normals_to_smooth_triangles() {
+        let obj = ObjFile::parse(
+            "v 0 1 0\nv -1 0 0\nv 1 0 0\n\
+             vn 0 1 0\nvn -1 0 0\nvn 1 0 0\n\
+             f 1//1 2//2 3//3\n",
+        )
+        .unwrap();
+        let shapes = obj.to_shapes();
+        let r = Ray::new(point(-0.2, 0.3, -2.0), vector(0, 0, 1));
+        let xs = shapes[0].intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_near!(
+            shapes[0].normal_at_hit(point(-0.2, 0.3, 0.0), &xs[0]),
+            vector(-0.5547, 0.83205, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_convert_to_group() {
+        let obj = ObjFile::parse(
+            "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\n\
+             f 1 2 3\ng FirstGroup\nf 1 2 3\ng SecondGroup\nf 1 3 4\n",
+        )
+        .unwrap();
+        let g = obj.to_group();
+        assert_eq!(g.children().len(), 3);
+        let r = Ray::new(point(0.5, 0.75, -2.0), vector(0, 0, 1));
+        let xs = g.intersect(&r);
+        assert_eq!(ts(xs.clone()), vec![2.0]);
+        assert_eq!(xs[0].object.normal_at(r.position(2.0)), vector(0, 0, -1));
+    }
+}
@@ -0,0 +1,308 @@
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::intersect::hit;
+use crate::light::Light;
+use crate::random::Rng;
+use crate::ray::Ray;
+use crate::vector::dot;
+use crate::world::{Computations, World};
+
+/// Renders a world by Monte Carlo path tracing, which unlike
+/// `World::color_at` includes indirect light and color bleeding.
+///
+/// Each path gathers the light of `World::lights` at every diffuse bounce
+/// with `World::direct_light`, and then continues in a direction sampled from
+/// the BSDF of the shader. `reflective` and `transparency` are the chances of
+/// a mirror reflection and a refraction instead of a diffuse bounce, blended
+/// with Schlick's approximation when both are set. Emissive objects and the
+/// background of the world are seen directly and light the scene. Lights
+/// infinitely far away, like an `EnvironmentLight`, stand for the background
+/// once a path has bounced off a diffuse surface, and should show the same
+/// image. `Material::ambient` is ignored.
+#[derive(Debug, Clone)]
+pub struct PathTracer {
+    pub samples_per_pixel: usize,
+    /// Bounces after which paths may be terminated by Russian roulette.
+    pub roulette_depth: usize,
+    /// Bounces after which paths are always terminated.
+    pub max_depth: usize,
+    pub seed: u64,
+}
+
+impl PathTracer {
+    pub fn new(samples_per_pixel: usize) -> PathTracer {
+        PathTracer {
+            samples_per_pixel,
+            roulette_depth: 3,
+            max_depth: 32,
+            seed: 0,
+        }
+    }
+
+    pub fn render(&self, camera: &Camera, w: &World) -> Canvas {
+        let mut rng = Rng::new(self.seed);
+        let mut image = Canvas::new(camera.hsize(), camera.vsize(), Color::black());
+        for y in 0..camera.vsize() {
+            for x in 0..camera.hsize() {
+                image.set(x, y, self.render_pixel(camera, w, x, y, &mut rng));
+            }
+        }
+        image
+    }
+
+    /// Averages `samples_per_pixel` paths through random points in the
+    /// pixel.
+    pub fn render_pixel(
+        &self,
+        camera: &Camera,
+        w: &World,
+        x: usize,
+        y: usize,
+        rng: &mut Rng,
+    ) -> Color {
+        let mut sum = Color::black();
+        for _ in 0..self.samples_per_pixel {
+            let r = camera.ray_for_subpixel(x, y, rng.next_f32(), rng.next_f32());
+            sum = sum + self.trace(w, &r, rng);
+        }
+        (1.0 / self.samples_per_pixel as f32) * sum
+    }
+
+    /// Estimates the light arriving along the ray with a single random path.
+    pub fn trace(&self, w: &World, r: &Ray, rng: &mut Rng) -> Color {
+        let mut radiance = Color::black();
+        let mut throughput = Color::white();
+        let mut ray = *r;
+        // Whether the ray comes from the eye or from a mirror reflection or
+        // a refraction. After a diffuse bounce the light of emissive objects
+        // was already gathered by `World::direct_light`.
+        let mut specular = true;
+        for depth in 0..=self.max_depth {
+            let xs = w.intersect(&ray);
+            let Some(x) = hit(&xs) else {
+                if specular || !w.lights.iter().any(|l| fills_background(l.as_ref(), &ray)) {
+                    radiance = radiance + throughput * w.background.color_for(ray.direction);
+                }
+                break;
+            };
+            let comps = Computations::new(x, &ray, &xs);
+            let m = comps.object.material_at(comps.point);
+            if specular {
+                radiance = radiance + throughput * m.emission;
+            }
+
+            let (reflect, transmit) = if m.reflective > 0.0 && m.transparency > 0.0 {
+                let reflectance = comps.schlick();
+                (reflectance, 1.0 - reflectance)
+            } else {
+                (m.reflective, m.transparency)
+            };
+            let choice = rng.next_f32();
+            specular = choice < reflect + transmit;
+            ray = if choice < reflect {
+                Ray::new(comps.over_point, comps.reflect_vec)
+            } else if choice < reflect + transmit {
+                match comps.refract_vec() {
+                    Some(direction) => Ray::new(comps.under_point, direction),
+                    None => Ray::new(comps.over_point, comps.reflect_vec),
+                }
+            } else {
+                radiance = radiance + throughput * w.direct_light(&comps, rng);
+                let (v, n) = (comps.eye_vec, comps.normal_vec);
+                let direction = m
+                    .shader
+                    .sample_direction(&m, v, n, rng.next_f32(), rng.next_f32());
+                let pdf = m.shader.pdf(&m, direction, v, n);
+                if pdf <= 0.0 {
+                    break;
+                }
+                let bsdf = m.shader.bsdf(&m, direction, v, n);
+                throughput = throughput * ((dot(direction, n) / pdf) * bsdf);
+                Ray::new(comps.over_point, direction)
+            };
+
+            if depth >= self.roulette_depth {
+                let survival = throughput
+                    .red
+                    .max(throughput.green)
+                    .max(throughput.blue)
+                    .min(0.95);
+                if rng.next_f32() >= survival {
+                    break;
+                }
+                throughput = (1.0 / survival) * throughput;
+            }
+        }
+        radiance
+    }
+}
+
+/// Whether rays escaping the scene along `ray` meet the light infinitely far
+/// away, in which case `World::direct_light` gathered what they see.
+fn fills_background(l: &dyn Light, ray: &Ray) -> bool {
+    l.hit(ray.origin, ray.direction)
+        .is_some_and(|(sample, _)| sample.distance.is_infinite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::{Background, EnvironmentMap};
+    use crate::light::{EnvironmentLight, PointLight};
+    use crate::material::Material;
+    use crate::point::point;
+    use crate::sphere::Sphere;
+    use crate::test_utils::*;
+    use crate::vector::vector;
+    use crate::view::view_transform;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_trace_single_diffuse_bounce() {
+        let mut w = World::new();
+        w.lights
+            .push(Box::new(PointLight::new(point(0, 0, -10), Color::white())));
+        w.add_object(Box::new(Sphere::new()));
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let mut rng = Rng::new(0);
+        // Bounced rays escape the scene, so only the direct light remains.
+        let c = PathTracer::new(1).trace(&w, &r, &mut rng);
+        let expected = 0.9 / PI;
+        assert_near!(c, Color::new(expected, expected, expected));
+    }
+
+    #[test]
+    fn test_trace_inside_closed_room_converges() {
+        // Inside a sphere lit from its center, every point of the wall
+        // receives the same direct light and the same light from the rest of
+        // the wall, so the radiance is the geometric series
+        // (albedo / pi) * (1 + albedo + albedo^2 + ...).
+        let albedo = 0.5;
+        let mut w = World::new();
+        w.lights
+            .push(Box::new(PointLight::new(point(0, 0, 0), Color::white())));
+        let mut s = Sphere::new();
+        let mut m = Material::new();
+        m.diffuse = albedo;
+        s.set_material(m);
+        w.add_object(Box::new(s));
+
+        let tracer = PathTracer::new(1);
+        let mut rng = Rng::new(0);
+        let r = Ray::new(point(0, 0, 0), vector(0, 0, 1));
+        let count = 4000;
+        let mut sum = Color::black();
+        for _ in 0..count {
+            sum = sum + tracer.trace(&w, &r, &mut rng);
+        }
+        let expected = albedo / (PI * (1.0 - albedo));
+        assert_near!(
+            (1.0 / count as f32) * sum,
+            Color::new(expected, expected, expected),
+            1e-2
+        );
+
+        let mut direct_only = PathTracer::new(1);
+        direct_only.max_depth = 0;
+        let c = direct_only.trace(&w, &r, &mut rng);
+        assert_near!(c.red, albedo / PI);
+    }
+
+    #[test]
+    fn test_render_is_reproducible() {
+        let w = crate::world::default_world();
+        let mut camera = Camera::new(8, 6, PI / 2.0);
+        camera
+            .set_transform(view_transform(
+                point(0, 0, -5),
+                point(0, 0, 0),
+                vector(0, 1, 0),
+            ))
+            .unwrap();
+        let tracer = PathTracer::new(4);
+        let image = tracer.render(&camera, &w);
+        assert_eq!(image, tracer.render(&camera, &w));
+        assert_ne!(image.get(4, 3), Color::black());
+    }
+
+    #[test]
+    fn test_trace_sees_emissive_object() {
+        let mut w = World::new();
+        let mut s = Sphere::new();
+        let mut m = Material::new();
+        m.emission = Color::new(2.0, 1.0, 0.5);
+        s.set_material(m);
+        w.add_object(Box::new(s));
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let mut rng = Rng::new(0);
+        for _ in 0..10 {
+            let c = PathTracer::new(1).trace(&w, &r, &mut rng);
+            assert_near!(c, Color::new(2.0, 1.0, 0.5));
+        }
+    }
+
+    #[test]
+    fn test_trace_emission_through_mirror() {
+        let mut w = World::new();
+        let mut mirror = Sphere::new();
+        let mut m = Material::new();
+        m.reflective = 1.0;
+        mirror.set_material(m);
+        w.add_object(Box::new(mirror));
+        let mut lamp = Sphere::new();
+        lamp.set_transform(crate::transforms::translation(0.0, 0.0, -5.0))
+            .unwrap();
+        let mut m = Material::new();
+        m.emission = Color::white();
+        lamp.set_material(m);
+        w.add_object(Box::new(lamp));
+        let r = Ray::new(point(0.0, 0.0, -2.0), vector(0, 0, 1));
+        let mut rng = Rng::new(0);
+        let c = PathTracer::new(1).trace(&w, &r, &mut rng);
+        assert_near!(c, Color::white());
+    }
+
+    #[test]
+    fn test_trace_white_furnace() {
+        // A diffuse sphere under a uniform white sky reflects its albedo,
+        // whatever the angle, as the light it bounces never returns to it.
+        let mut w = World::new();
+        w.background = Background::Color(Color::white());
+        let mut s = Sphere::new();
+        let mut m = Material::new();
+        m.diffuse = 0.5;
+        s.set_material(m);
+        w.add_object(Box::new(s));
+        let mut tracer = PathTracer::new(1);
+        tracer.max_depth = 1;
+        let r = Ray::new(point(0.5, 0.0, -5.0), vector(0, 0, 1));
+        let mut rng = Rng::new(0);
+        for _ in 0..10 {
+            assert_near!(tracer.trace(&w, &r, &mut rng), Color::new(0.5, 0.5, 0.5));
+        }
+    }
+
+    #[test]
+    fn test_trace_environment_light_is_not_counted_twice() {
+        let sky = EnvironmentMap::new(Canvas::new(16, 8, Color::white())).unwrap();
+        let mut w = World::new();
+        w.background = Background::Environment(sky.clone());
+        w.lights.push(Box::new(EnvironmentLight::new(sky)));
+        let mut s = Sphere::new();
+        let mut m = Material::new();
+        m.diffuse = 0.5;
+        s.set_material(m);
+        w.add_object(Box::new(s));
+        let mut tracer = PathTracer::new(1);
+        tracer.max_depth = 1;
+        let r = Ray::new(point(0.5, 0.0, -5.0), vector(0, 0, 1));
+        let mut rng = Rng::new(0);
+        let count = 2000;
+        let mut sum = Color::black();
+        for _ in 0..count {
+            sum = sum + tracer.trace(&w, &r, &mut rng);
+        }
+        assert_near!((1.0 / count as f32) * sum, Color::new(0.5, 0.5, 0.5), 2e-2);
+    }
+}
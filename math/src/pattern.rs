@@ -0,0 +1,449 @@
+use crate::color::Color;
+use crate::matrix::Matrix;
+use crate::noise;
+use crate::point::Point;
+use crate::transformation::Transformation;
+use crate::transforms::Transform;
+
+/// A color that varies over space, assigned to a `Material`.
+///
+/// Patterns are defined in their own pattern space, which is placed in the
+/// object space of the shape by the transform of the pattern.
+pub trait Pattern: std::fmt::Debug {
+    fn transform(&self) -> &Transformation;
+
+    /// Returns the color at a point given in pattern space.
+    fn local_pattern_at(&self, pattern_point: Point) -> Color;
+
+    /// Returns the color at a point given in object space.
+    fn pattern_at(&self, object_point: Point) -> Color {
+        self.local_pattern_at(object_point.apply(self.transform().inverse()))
+    }
+}
+
+/// Patterns are compared by identity, like shapes.
+impl<'a> PartialEq for dyn Pattern + 'a {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+/// Alternates between `a` and `b` along the x axis.
+#[derive(Debug, PartialEq)]
+pub struct StripePattern {
+    transform: Transformation,
+    pub a: Color,
+    pub b: Color,
+}
+
+impl StripePattern {
+    pub fn new(a: Color, b: Color) -> StripePattern {
+        StripePattern {
+            transform: Transformation::identity(),
+            a,
+            b,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+}
+
+/// Blends from `a` to `b` along the x axis, repeating every unit.
+#[derive(Debug, PartialEq)]
+pub struct GradientPattern {
+    transform: Transformation,
+    pub a: Color,
+    pub b: Color,
+}
+
+impl GradientPattern {
+    pub fn new(a: Color, b: Color) -> GradientPattern {
+        GradientPattern {
+            transform: Transformation::identity(),
+            a,
+            b,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+}
+
+/// Alternates between `a` and `b` in rings around the y axis.
+#[derive(Debug, PartialEq)]
+pub struct RingPattern {
+    transform: Transformation,
+    pub a: Color,
+    pub b: Color,
+}
+
+impl RingPattern {
+    pub fn new(a: Color, b: Color) -> RingPattern {
+        RingPattern {
+            transform: Transformation::identity(),
+            a,
+            b,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+}
+
+/// Alternates between `a` and `b` in unit cubes.
+#[derive(Debug, PartialEq)]
+pub struct CheckerPattern {
+    transform: Transformation,
+    pub a: Color,
+    pub b: Color,
+}
+
+impl CheckerPattern {
+    pub fn new(a: Color, b: Color) -> CheckerPattern {
+        CheckerPattern {
+            transform: Transformation::identity(),
+            a,
+            b,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+}
+
+/// Veins of `b` through `a`, bent by turbulence.
+#[derive(Debug, PartialEq)]
+pub struct MarblePattern {
+    transform: Transformation,
+    pub a: Color,
+    pub b: Color,
+    /// How far the veins are pushed around by the turbulence.
+    pub strength: f32,
+    pub octaves: usize,
+}
+
+impl MarblePattern {
+    pub fn new(a: Color, b: Color) -> MarblePattern {
+        MarblePattern {
+            transform: Transformation::identity(),
+            a,
+            b,
+            strength: 5.0,
+            octaves: 6,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+}
+
+/// Rings around the y axis going from `a` to `b`, distorted by noise.
+#[derive(Debug, PartialEq)]
+pub struct WoodPattern {
+    transform: Transformation,
+    pub a: Color,
+    pub b: Color,
+    /// How much the noise distorts the rings.
+    pub strength: f32,
+}
+
+impl WoodPattern {
+    pub fn new(a: Color, b: Color) -> WoodPattern {
+        WoodPattern {
+            transform: Transformation::identity(),
+            a,
+            b,
+            strength: 0.5,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+}
+
+/// Grains of `b` speckled over `a`.
+#[derive(Debug, PartialEq)]
+pub struct GranitePattern {
+    transform: Transformation,
+    pub a: Color,
+    pub b: Color,
+    pub octaves: usize,
+}
+
+impl GranitePattern {
+    pub fn new(a: Color, b: Color) -> GranitePattern {
+        GranitePattern {
+            transform: Transformation::identity(),
+            a,
+            b,
+            octaves: 8,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+}
+
+fn is_even(x: f32) -> bool {
+    x.floor().rem_euclid(2.0) == 0.0
+}
+
+impl Pattern for StripePattern {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn local_pattern_at(&self, p: Point) -> Color {
+        if is_even(p.x) {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+impl Pattern for GradientPattern {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn local_pattern_at(&self, p: Point) -> Color {
+        self.a + (self.b - self.a) * (p.x - p.x.floor())
+    }
+}
+
+impl Pattern for RingPattern {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn local_pattern_at(&self, p: Point) -> Color {
+        if is_even((p.x * p.x + p.z * p.z).sqrt()) {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+impl Pattern for CheckerPattern {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn local_pattern_at(&self, p: Point) -> Color {
+        if is_even(p.x.floor() + p.y.floor() + p.z.floor()) {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+impl Pattern for MarblePattern {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn local_pattern_at(&self, p: Point) -> Color {
+        let t = 0.5 + 0.5 * (p.x + self.strength * noise::turbulence(p, self.octaves)).sin();
+        self.a + (self.b - self.a) * t
+    }
+}
+
+impl Pattern for WoodPattern {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn local_pattern_at(&self, p: Point) -> Color {
+        let g = (p.x * p.x + p.z * p.z).sqrt() + self.strength * noise::perlin(p);
+        self.a + (self.b - self.a) * (g - g.floor())
+    }
+}
+
+impl Pattern for GranitePattern {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn local_pattern_at(&self, p: Point) -> Color {
+        let t = (2.0 * noise::turbulence(p, self.octaves)).min(1.0);
+        self.a + (self.b - self.a) * t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::point::point;
+    use crate::shape::Shape;
+    use crate::sphere::Sphere;
+    use crate::test_utils::*;
+    use crate::transforms::{scaling, translation};
+    use std::rc::Rc;
+
+    fn white() -> Color {
+        Color::new(1., 1., 1.)
+    }
+
+    #[test]
+    fn test_create_stripe_pattern() {
+        let p = StripePattern::new(white(), Color::black());
+        assert_eq!(p.a, white());
+        assert_eq!(p.b, Color::black());
+        assert_eq!(p.transform().matrix(), &Matrix::identity());
+    }
+
+    #[test]
+    fn test_stripe_pattern_is_constant_in_y_and_z() {
+        let p = StripePattern::new(white(), Color::black());
+        for q in [point(0, 0, 0), point(0, 1, 0), point(0, 2, 0)] {
+            assert_eq!(p.local_pattern_at(q), white());
+        }
+        for q in [point(0, 0, 0), point(0, 0, 1), point(0, 0, 2)] {
+            assert_eq!(p.local_pattern_at(q), white());
+        }
+    }
+
+    #[test]
+    fn test_stripe_pattern_alternates_in_x() {
+        let p = StripePattern::new(white(), Color::black());
+        let cases = [
+            (0.0, white()),
+            (0.9, white()),
+            (1.0, Color::black()),
+            (-0.1, Color::black()),
+            (-1.0, Color::black()),
+            (-1.1, white()),
+        ];
+        for (x, c) in cases {
+            assert_eq!(p.local_pattern_at(point(x, 0.0, 0.0)), c);
+        }
+    }
+
+    #[test]
+    fn test_pattern_with_transformation() {
+        let mut p = StripePattern::new(white(), Color::black());
+        p.set_transform(scaling(2., 2., 2.)).unwrap();
+        assert_eq!(p.pattern_at(point(1.5, 0.0, 0.0)), white());
+        assert_eq!(p.pattern_at(point(2.5, 0.0, 0.0)), Color::black());
+    }
+
+    #[test]
+    fn test_gradient_linearly_interpolates_between_colors() {
+        let p = GradientPattern::new(white(), Color::black());
+        let cases = [
+            (0.0, white()),
+            (0.25, Color::new(0.75, 0.75, 0.75)),
+            (0.5, Color::new(0.5, 0.5, 0.5)),
+            (0.75, Color::new(0.25, 0.25, 0.25)),
+        ];
+        for (x, c) in cases {
+            assert_near!(p.local_pattern_at(point(x, 0.0, 0.0)), c);
+        }
+    }
+
+    #[test]
+    fn test_ring_extends_in_x_and_z() {
+        let p = RingPattern::new(white(), Color::black());
+        assert_eq!(p.local_pattern_at(point(0, 0, 0)), white());
+        assert_eq!(p.local_pattern_at(point(1, 0, 0)), Color::black());
+        assert_eq!(p.local_pattern_at(point(0, 0, 1)), Color::black());
+        assert_eq!(p.local_pattern_at(point(0.708, 0.0, 0.708)), Color::black());
+    }
+
+    #[test]
+    fn test_checkers_repeat_in_each_dimension() {
+        let p = CheckerPattern::new(white(), Color::black());
+        for (x, y, z) in [(0.99, 0.0, 0.0), (0.0, 0.99, 0.0), (0.0, 0.0, 0.99)] {
+            assert_eq!(p.local_pattern_at(point(x, y, z)), white());
+        }
+        for (x, y, z) in [(1.01, 0.0, 0.0), (0.0, 1.01, 0.0), (0.0, 0.0, 1.01)] {
+            assert_eq!(p.local_pattern_at(point(x, y, z)), Color::black());
+        }
+    }
+
+    fn striped_sphere(object: Matrix<4, 4>, pattern: Matrix<4, 4>) -> Sphere {
+        let mut p = StripePattern::new(white(), Color::black());
+        p.set_transform(pattern).unwrap();
+        let mut s = Sphere::new();
+        s.set_transform(object).unwrap();
+        s.set_material({
+            let mut m = Material::new();
+            m.pattern = Some(Rc::new(p));
+            m
+        });
+        s
+    }
+
+    #[test]
+    fn test_stripes_with_object_transformation() {
+        let s = striped_sphere(scaling(2., 2., 2.), Matrix::identity());
+        assert_eq!(s.material_at(point(1.5, 0.0, 0.0)).color, white());
+    }
+
+    #[test]
+    fn test_stripes_with_pattern_transformation() {
+        let s = striped_sphere(Matrix::identity(), scaling(2., 2., 2.));
+        assert_eq!(s.material_at(point(1.5, 0.0, 0.0)).color, white());
+    }
+
+    #[test]
+    fn test_stripes_with_object_and_pattern_transformation() {
+        let s = striped_sphere(scaling(2., 2., 2.), translation(0.5, 0.0, 0.0));
+        assert_eq!(s.material_at(point(2.5, 0.0, 0.0)).color, white());
+        assert_eq!(s.material_at(point(3.5, 0.0, 0.0)).color, Color::black());
+    }
+
+    #[test]
+    fn test_marble_is_between_colors_at_origin() {
+        let p = MarblePattern::new(white(), Color::black());
+        assert_near!(
+            p.local_pattern_at(point(0, 0, 0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn test_wood_starts_at_first_color_on_axis() {
+        let p = WoodPattern::new(white(), Color::black());
+        assert_eq!(p.local_pattern_at(point(0, 0, 0)), white());
+        assert_eq!(p.local_pattern_at(point(0, 3, 0)), white());
+    }
+
+    #[test]
+    fn test_solid_textures_stay_between_colors() {
+        let a = Color::new(0.2, 0.4, 0.6);
+        let b = Color::new(0.8, 0.6, 0.4);
+        let patterns: Vec<Box<dyn Pattern>> = vec![
+            Box::new(MarblePattern::new(a, b)),
+            Box::new(WoodPattern::new(a, b)),
+            Box::new(GranitePattern::new(a, b)),
+        ];
+        for pattern in &patterns {
+            for i in 0..100 {
+                let i = i as f32;
+                let c = pattern.local_pattern_at(point(i * 0.31, i * 0.17, -i * 0.23));
+                assert!((0.2 - 1e-5..=0.8 + 1e-5).contains(&c.red));
+                assert!((0.4 - 1e-5..=0.6 + 1e-5).contains(&c.green));
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::approx::EPSILON;
+use crate::bounds::Bounds;
+use crate::intersect::Intersection;
+use crate::material::Material;
+use crate::matrix::Matrix;
+use crate::point::{point, Point};
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::transformation::Transformation;
+use crate::vector::{vector, Vector};
+
+#[derive(Debug, PartialEq)]
+pub struct Plane {
+    transform: Transformation,
+    material: Material,
+}
+
+impl Plane {
+    pub fn new() -> Plane {
+        Plane {
+            transform: Transformation::identity(),
+            material: Material::new(),
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Plane::new()
+    }
+}
+
+impl Shape for Plane {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Transformation) {
+        self.transform.set_parent(parent);
+    }
+
+    fn local_intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
+        if r.direction.y.abs() < EPSILON {
+            vec![]
+        } else {
+            vec![Intersection::new(self, -r.origin.y / r.direction.y)]
+        }
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            point(f32::NEG_INFINITY, 0.0, f32::NEG_INFINITY),
+            point(f32::INFINITY, 0.0, f32::INFINITY),
+        )
+    }
+
+    fn local_normal_at(&self, _object_point: Point) -> Vector {
+        vector(0, 1, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::point;
+    use crate::test_utils::*;
+    use crate::transforms::{rotation_z, translation};
+    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+    #[test]
+    fn test_normal_is_constant_everywhere() {
+        let p = Plane::new();
+        assert_eq!(p.normal_at(point(0, 0, 0)), vector(0, 1, 0));
+        assert_eq!(p.normal_at(point(10, 0, -10)), vector(0, 1, 0));
+        assert_eq!(p.normal_at(point(-5, 0, 150)), vector(0, 1, 0));
+    }
+
+    #[test]
+    fn test_intersect_with_parallel_ray() {
+        let p = Plane::new();
+        let r = Ray::new(point(0, 10, 0), vector(0, 0, 1));
+        assert_eq!(ts(p.intersect(&r)), vec![]);
+    }
+
+    #[test]
+    fn test_intersect_with_coplanar_ray() {
+        let p = Plane::new();
+        let r = Ray::new(point(0, 0, 0), vector(0, 0, 1));
+        assert_eq!(ts(p.intersect(&r)), vec![]);
+    }
+
+    #[test]
+    fn test_intersect_from_above() {
+        let p = Plane::new();
+        let r = Ray::new(point(0, 1, 0), vector(0, -1, 0));
+        assert_eq!(ts(p.intersect(&r)), vec![1.0]);
+    }
+
+    #[test]
+    fn test_intersect_from_below() {
+        let p = Plane::new();
+        let r = Ray::new(point(0, -1, 0), vector(0, 1, 0));
+        assert_eq!(ts(p.intersect(&r)), vec![1.0]);
+    }
+
+    #[test]
+    fn test_intersect_translated_plane() {
+        let mut p = Plane::new();
+        p.set_transform(translation(0., -1., 0.)).unwrap();
+        let r = Ray::new(point(0, 1, 0), vector(0, -1, 0));
+        assert_eq!(ts(p.intersect(&r)), vec![2.0]);
+    }
+
+    #[test]
+    fn test_normal_of_rotated_plane() {
+        let mut p = Plane::new();
+        p.set_transform(rotation_z(PI / 4.0)).unwrap();
+        assert_near!(
+            p.normal_at(point(0, 0, 0)),
+            vector(-FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_default_transform() {
+        assert_eq!(Plane::new().transform.matrix(), &Matrix::<4, 4>::identity());
+    }
+
+    #[test]
+    fn test_default_material() {
+        assert_eq!(Plane::new().material(), Material::new());
+    }
+
+    #[test]
+    fn test_assigned_material() {
+        let mut p = Plane::new();
+        let mut m = Material::new();
+        m.ambient = 1.0;
+        p.set_material(m.clone());
+        assert_eq!(p.material(), m);
+    }
+
+    #[test]
+    fn test_plane_bounds() {
+        let b = Plane::new().bounds();
+        assert_eq!(b.min, point(f32::NEG_INFINITY, 0.0, f32::NEG_INFINITY));
+        assert_eq!(b.max, point(f32::INFINITY, 0.0, f32::INFINITY));
+    }
+}
@@ -3,6 +3,7 @@ use crate::point::Point;
 use crate::transforms::Transform;
 use crate::vector::Vector;
 
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Ray {
     pub origin: Point,
     pub direction: Vector,
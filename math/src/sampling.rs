@@ -0,0 +1,126 @@
+use crate::vector::{dot, vector, Vector};
+use std::f32::consts::PI;
+
+/// Returns two unit vectors perpendicular to the unit vector `n` and to each
+/// other (Duff et al., "Building an orthonormal basis, revisited").
+pub fn orthonormal_basis(n: Vector) -> (Vector, Vector) {
+    let sign = 1.0f32.copysign(n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    (
+        vector(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+        vector(b, sign + n.y * n.y * a, -n.y),
+    )
+}
+
+/// Maps `u1` and `u2`, uniform in `[0, 1)`, to a direction in the hemisphere
+/// around the unit normal `n`, distributed with density `cos / pi` where
+/// `cos` is the cosine to the normal.
+pub fn cosine_weighted(n: Vector, u1: f32, u2: f32) -> Vector {
+    let r = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    let (t, b) = orthonormal_basis(n);
+    t * (r * phi.cos()) + b * (r * phi.sin()) + n * (1.0 - u1).max(0.0).sqrt()
+}
+
+/// Probability density of `cosine_weighted` picking `direction`.
+pub fn cosine_weighted_pdf(n: Vector, direction: Vector) -> f32 {
+    dot(n, direction).max(0.0) / PI
+}
+
+/// Weight of a sample taken with density `pdf` when combined by multiple
+/// importance sampling with a strategy of density `other_pdf`.
+pub fn balance_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    pdf / (pdf + other_pdf)
+}
+
+/// Like `balance_heuristic`, but with the densities squared, which favors
+/// the better strategy more and usually gives less noise.
+pub fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    a / (a + b)
+}
+
+/// Picks a bin of `cdf`, the running totals of a distribution normalized to
+/// end in 1, with `u` uniform in `[0, 1)`. Returns the bin with where `u`
+/// falls inside it, from 0 to 1, which is uniform as well.
+pub fn sample_cdf(cdf: &[f32], u: f32) -> (usize, f32) {
+    let i = cdf.partition_point(|&c| c <= u).min(cdf.len() - 1);
+    let start = if i == 0 { 0.0 } else { cdf[i - 1] };
+    let width = cdf[i] - start;
+    let offset = if width > 0.0 {
+        ((u - start) / width).clamp(0.0, 1.0)
+    } else {
+        0.5
+    };
+    (i, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_orthonormal_basis() {
+        for n in [
+            vector(0, 0, 1),
+            vector(0, 0, -1),
+            vector(1, 0, 0),
+            vector(1.0, -2.0, 3.0).normalized(),
+        ] {
+            let (t, b) = orthonormal_basis(n);
+            assert_near!(t.len(), 1.0);
+            assert_near!(b.len(), 1.0);
+            assert_near!(dot(t, n), 0.0);
+            assert_near!(dot(b, n), 0.0);
+            assert_near!(dot(t, b), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_cosine_weighted_directions() {
+        let n = vector(0.0, 1.0, 1.0).normalized();
+        let mut rng = Rng::new(1);
+        let count = 10000;
+        let mut sum = 0.0;
+        for _ in 0..count {
+            let d = cosine_weighted(n, rng.next_f32(), rng.next_f32());
+            assert_near!(d.len(), 1.0, 1e-4);
+            assert!(dot(d, n) >= 0.0);
+            sum += dot(d, n);
+        }
+        // The mean cosine of the distribution is 2/3.
+        assert_near!(sum / count as f32, 2.0 / 3.0, 1e-2);
+    }
+
+    #[test]
+    fn test_cosine_weighted_pdf() {
+        let n = vector(0, 0, 1);
+        assert_near!(cosine_weighted_pdf(n, n), 1.0 / PI);
+        assert_eq!(cosine_weighted_pdf(n, vector(0, 0, -1)), 0.0);
+    }
+
+    #[test]
+    fn test_sample_cdf() {
+        let cdf = [0.25, 0.25, 1.0];
+        assert_eq!(sample_cdf(&cdf, 0.0), (0, 0.0));
+        assert_eq!(sample_cdf(&cdf, 0.125), (0, 0.5));
+        // Empty bins are never picked.
+        assert_eq!(sample_cdf(&cdf, 0.25), (2, 0.0));
+        assert_eq!(sample_cdf(&cdf, 0.625), (2, 0.5));
+        let (i, offset) = sample_cdf(&cdf, 0.99999);
+        assert_eq!(i, 2);
+        assert_near!(offset, 1.0, 1e-4);
+    }
+
+    #[test]
+    fn test_heuristics() {
+        assert_eq!(balance_heuristic(1.0, 1.0), 0.5);
+        assert_eq!(balance_heuristic(3.0, 1.0), 0.75);
+        assert_eq!(power_heuristic(3.0, 1.0), 0.9);
+        assert_eq!(power_heuristic(0.0, 2.0), 0.0);
+        assert_eq!(power_heuristic(2.0, 3.0) + power_heuristic(3.0, 2.0), 1.0);
+    }
+}
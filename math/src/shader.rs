@@ -0,0 +1,320 @@
+use crate::color::Color;
+use crate::light::{Light, LightSample};
+use crate::lighting;
+use crate::material::Material;
+use crate::sampling;
+use crate::vector::Vector;
+use std::any::Any;
+use std::f32::consts::PI;
+
+/// A shading model, assigned to a `Material`.
+///
+/// Shaders are compared by value, which `ShaderEq` provides for every
+/// shader that is `PartialEq`.
+pub trait Shader: Any + ShaderEq + std::fmt::Debug {
+    /// Returns the light reflected towards the eye from a single light,
+    /// given its samples at the point with those in shadow turned black.
+    fn shade_light(
+        &self,
+        m: &Material,
+        l: &dyn Light,
+        samples: &[LightSample],
+        v: Vector,
+        n: Vector,
+    ) -> Color;
+
+    /// Returns the color of a hit, before reflection and refraction are
+    /// added, `light_samples` giving the samples of a light for
+    /// `shade_light`. Sums `shade_light` over the lights by default.
+    fn shade(
+        &self,
+        m: &Material,
+        lights: &[Box<dyn Light>],
+        v: Vector,
+        n: Vector,
+        light_samples: &mut dyn FnMut(&dyn Light) -> Vec<LightSample>,
+    ) -> Color {
+        let mut surface = Color::black();
+        for light in lights {
+            let light = light.as_ref();
+            surface = surface + self.shade_light(m, light, &light_samples(light), v, n);
+        }
+        surface
+    }
+
+    /// The BSDF used by the path tracer: how much of the light arriving
+    /// from `l` leaves towards `v`, both unit vectors pointing away from the
+    /// surface. Lambertian with an albedo of `diffuse` times `color` by
+    /// default.
+    fn bsdf(&self, m: &Material, _l: Vector, _v: Vector, _n: Vector) -> Color {
+        (m.diffuse / PI) * m.color
+    }
+
+    /// Picks a random direction `l` for light to arrive from, `u1` and `u2`
+    /// being uniform in `[0, 1)`. Cosine weighted by default.
+    fn sample_direction(&self, _m: &Material, _v: Vector, n: Vector, u1: f32, u2: f32) -> Vector {
+        sampling::cosine_weighted(n, u1, u2)
+    }
+
+    /// Probability density of `sample_direction` picking `l`.
+    fn pdf(&self, _m: &Material, l: Vector, _v: Vector, n: Vector) -> f32 {
+        sampling::cosine_weighted_pdf(n, l)
+    }
+}
+
+/// Comparison of shaders behind `dyn Shader`, which cannot require
+/// `PartialEq` itself.
+pub trait ShaderEq {
+    /// Whether `other` is a shader of the same type with equal parameters.
+    fn eq_shader(&self, other: &dyn Shader) -> bool;
+}
+
+impl<T: Shader + PartialEq> ShaderEq for T {
+    fn eq_shader(&self, other: &dyn Shader) -> bool {
+        (other as &dyn Any).downcast_ref::<T>() == Some(self)
+    }
+}
+
+impl PartialEq for dyn Shader {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_shader(other)
+    }
+}
+
+/// `lighting::phong`, the default.
+#[derive(Debug, PartialEq)]
+pub struct Phong;
+
+impl Shader for Phong {
+    fn shade_light(
+        &self,
+        m: &Material,
+        l: &dyn Light,
+        samples: &[LightSample],
+        v: Vector,
+        n: Vector,
+    ) -> Color {
+        lighting::phong(m, l, samples, v, n)
+    }
+}
+
+/// `lighting::blinn_phong`.
+#[derive(Debug, PartialEq)]
+pub struct BlinnPhong;
+
+impl Shader for BlinnPhong {
+    fn shade_light(
+        &self,
+        m: &Material,
+        l: &dyn Light,
+        samples: &[LightSample],
+        v: Vector,
+        n: Vector,
+    ) -> Color {
+        lighting::blinn_phong(m, l, samples, v, n)
+    }
+}
+
+/// `lighting::lambert`.
+#[derive(Debug, PartialEq)]
+pub struct Lambert;
+
+impl Shader for Lambert {
+    fn shade_light(
+        &self,
+        m: &Material,
+        l: &dyn Light,
+        samples: &[LightSample],
+        v: Vector,
+        n: Vector,
+    ) -> Color {
+        lighting::lambert(m, l, samples, v, n)
+    }
+}
+
+/// `lighting::toon` with `bands` levels of diffuse lighting.
+#[derive(Debug, PartialEq)]
+pub struct Toon {
+    pub bands: usize,
+}
+
+impl Toon {
+    pub fn new(bands: usize) -> Toon {
+        Toon { bands }
+    }
+}
+
+impl Shader for Toon {
+    fn shade_light(
+        &self,
+        m: &Material,
+        l: &dyn Light,
+        samples: &[LightSample],
+        v: Vector,
+        n: Vector,
+    ) -> Color {
+        lighting::toon(m, l, samples, v, n, self.bands)
+    }
+}
+
+/// `lighting::cook_torrance`, using the `metallic` and `roughness` of the
+/// material.
+#[derive(Debug, PartialEq)]
+pub struct CookTorrance;
+
+impl Shader for CookTorrance {
+    fn shade_light(
+        &self,
+        m: &Material,
+        l: &dyn Light,
+        samples: &[LightSample],
+        v: Vector,
+        n: Vector,
+    ) -> Color {
+        lighting::cook_torrance(m, l, samples, v, n)
+    }
+
+    fn bsdf(&self, m: &Material, l: Vector, v: Vector, n: Vector) -> Color {
+        lighting::cook_torrance_brdf(m, l, v, n)
+    }
+}
+
+/// Debug view that ignores the lights and colors the surface with its
+/// normal, mapping each component from `[-1, 1]` to `[0, 1]`.
+#[derive(Debug, PartialEq)]
+pub struct Normals;
+
+impl Normals {
+    fn color(n: Vector) -> Color {
+        Color::new((n.x + 1.0) / 2.0, (n.y + 1.0) / 2.0, (n.z + 1.0) / 2.0)
+    }
+}
+
+impl Shader for Normals {
+    fn shade_light(
+        &self,
+        _m: &Material,
+        _l: &dyn Light,
+        _samples: &[LightSample],
+        _v: Vector,
+        n: Vector,
+    ) -> Color {
+        Normals::color(n)
+    }
+
+    fn shade(
+        &self,
+        _m: &Material,
+        _lights: &[Box<dyn Light>],
+        _v: Vector,
+        n: Vector,
+        _light_samples: &mut dyn FnMut(&dyn Light) -> Vec<LightSample>,
+    ) -> Color {
+        Normals::color(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::PointLight;
+    use crate::point::point;
+    use crate::random::Rng;
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+    use crate::test_utils::*;
+    use crate::vector::vector;
+    use crate::world::{default_world, Computations, World, MAX_DEPTH};
+    use std::rc::Rc;
+
+    #[test]
+    fn test_shaders_delegate_to_lighting() {
+        let m = Material::new();
+        let light = PointLight::new(point(0, 10, -10), Color::white());
+        let (pos, v, n) = (point(0, 0, 0), vector(0, 0, -1), vector(0, 0, -1));
+        let samples = light.samples_at(pos, &mut Rng::new(0));
+        assert_eq!(
+            Phong.shade_light(&m, &light, &samples, v, n),
+            lighting::phong(&m, &light, &samples, v, n)
+        );
+        assert_eq!(
+            BlinnPhong.shade_light(&m, &light, &samples, v, n),
+            lighting::blinn_phong(&m, &light, &samples, v, n)
+        );
+        assert_eq!(
+            Lambert.shade_light(&m, &light, &samples, v, n),
+            lighting::lambert(&m, &light, &samples, v, n)
+        );
+        assert_eq!(
+            Toon::new(4).shade_light(&m, &light, &samples, v, n),
+            lighting::toon(&m, &light, &samples, v, n, 4)
+        );
+        assert_eq!(
+            CookTorrance.shade_light(&m, &light, &samples, v, n),
+            lighting::cook_torrance(&m, &light, &samples, v, n)
+        );
+    }
+
+    #[test]
+    fn test_materials_compare_shaders_by_value() {
+        let mut a = Material::new();
+        a.shader = Rc::new(Lambert);
+        let mut b = a.clone();
+        assert_eq!(a, b);
+        b.shader = Rc::new(Lambert);
+        assert_eq!(a, b);
+        b.shader = Rc::new(Phong);
+        assert_ne!(a, b);
+        a.shader = Rc::new(Toon::new(2));
+        b.shader = Rc::new(Toon::new(2));
+        assert_eq!(a, b);
+        b.shader = Rc::new(Toon::new(3));
+        assert_ne!(a, b);
+    }
+
+    fn shade_sphere(shader: Rc<dyn Shader>) -> Color {
+        let mut w = World::new();
+        w.lights.push(Box::new(PointLight::new(
+            point(-10, 10, -10),
+            Color::white(),
+        )));
+        let mut s = Sphere::new();
+        let mut m = Material::new();
+        m.shader = shader;
+        s.set_material(m);
+        w.add_object(Box::new(s));
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let xs = w.intersect(&r);
+        w.shade_hit(
+            Computations::new(xs[0], &r, &xs),
+            MAX_DEPTH,
+            &mut Rng::new(0),
+        )
+    }
+
+    #[test]
+    fn test_shade_hit_uses_material_shader() {
+        assert_near!(
+            shade_sphere(Rc::new(Lambert)),
+            Color::new(0.58320, 0.58320, 0.58320),
+            1e-4
+        );
+        assert_near!(shade_sphere(Rc::new(Normals)), Color::new(0.5, 0.5, 0.0));
+        assert_near!(shade_sphere(Rc::new(Toon::new(2))), Color::white());
+    }
+
+    #[test]
+    fn test_normals_shader_ignores_lights() {
+        let w = default_world();
+        let mut m = Material::new();
+        m.shader = Rc::new(Normals);
+        let (v, n) = (vector(0, 0, -1), vector(0, 0, -1));
+        let mut sampled = false;
+        let color = m.shader.shade(&m, &w.lights, v, n, &mut |_| {
+            sampled = true;
+            vec![]
+        });
+        assert_near!(color, Color::new(0.5, 0.5, 0.0));
+        assert!(!sampled);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::bounds::Bounds;
+use crate::intersect::Intersection;
+use crate::material::Material;
+use crate::point::Point;
+use crate::ray::Ray;
+use crate::transformation::Transformation;
+use crate::transforms::Transform;
+use crate::tuple::Tuple;
+use crate::vector::{vector, Vector};
+
+/// A primitive that can be placed into a `World`.
+///
+/// Implementors only describe the shape in its own object space; the
+/// world-to-object conversion of rays and normals is done by the provided
+/// `intersect` and `normal_at` methods.
+pub trait Shape: std::fmt::Debug {
+    fn transform(&self) -> &Transformation;
+
+    fn material(&self) -> Material;
+
+    /// Called by a `Group` when this shape is added to it or when the
+    /// transform of the group changes.
+    fn set_parent_transform(&mut self, parent: &Transformation);
+
+    /// Intersects a ray given in object space.
+    fn local_intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>>;
+
+    /// Returns the bounding box of the shape in object space.
+    fn bounds(&self) -> Bounds;
+
+    /// Returns the normal at a point given in object space.
+    ///
+    /// # Panics
+    ///
+    /// Shapes made of other shapes, like `Group` and `Csg`, have no surface
+    /// of their own and panic. Their intersections refer to the leaf shapes
+    /// that were hit, which give the normal.
+    fn local_normal_at(&self, object_point: Point) -> Vector;
+
+    /// Returns the normal at a point given in object space for a particular
+    /// hit, for shapes that interpolate normals across their surface.
+    fn local_normal_at_hit(&self, object_point: Point, _hit: &Intersection) -> Vector {
+        self.local_normal_at(object_point)
+    }
+
+    /// Returns the bounding box of the shape in the space of its parent.
+    fn parent_space_bounds(&self) -> Bounds {
+        self.bounds().transformed(self.transform().matrix())
+    }
+
+    fn intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
+        self.local_intersect(&r.apply(self.transform().inverse()))
+    }
+
+    /// Converts a world space point to object space, going through the
+    /// transforms of all parent groups.
+    fn world_to_object(&self, world_point: Point) -> Point {
+        world_point.apply(self.transform().world_inverse())
+    }
+
+    /// Converts an object space normal to world space, going through the
+    /// transforms of all parent groups.
+    fn normal_to_world(&self, object_normal: Vector) -> Vector {
+        let world_normal =
+            Tuple::from(object_normal).apply(self.transform().world_inverse_transpose());
+        vector(world_normal.x, world_normal.y, world_normal.z).normalized()
+    }
+
+    /// Returns the normal at a point given in world space.
+    ///
+    /// # Panics
+    ///
+    /// Panics when called on a `Group` or `Csg`, which have no surface of
+    /// their own. Call it on the leaf shape in `Intersection::object`
+    /// instead.
+    fn normal_at(&self, world_point: Point) -> Vector {
+        self.normal_to_world(self.local_normal_at(self.world_to_object(world_point)))
+    }
+
+    /// Returns the normal at a point given in world space for a particular
+    /// hit, see `local_normal_at_hit`.
+    ///
+    /// # Panics
+    ///
+    /// Panics for a `Group` or `Csg`, like `normal_at`.
+    fn normal_at_hit(&self, world_point: Point, hit: &Intersection) -> Vector {
+        let object_point = self.world_to_object(world_point);
+        self.normal_to_world(self.local_normal_at_hit(object_point, hit))
+    }
+
+    /// Returns the material with its pattern, if any, evaluated at the point.
+    fn material_at(&self, world_point: Point) -> Material {
+        let mut m = self.material();
+        if let Some(pattern) = &m.pattern {
+            m.color = pattern.pattern_at(self.world_to_object(world_point));
+        }
+        m
+    }
+
+    /// Shapes directly inside this one, for walking the scene.
+    fn children(&self) -> &[Box<dyn Shape>] {
+        &[]
+    }
+
+    /// Returns the area of the surface in world space, for shapes that are
+    /// sampled as lights when their material is emissive.
+    fn area(&self) -> Option<f32> {
+        None
+    }
+
+    /// Picks a uniformly distributed point on the surface, `u1` and `u2`
+    /// being uniform in `[0, 1)`, and returns it with the normal there, both
+    /// in world space. Only for shapes with an `area`.
+    fn sample_surface(&self, _u1: f32, _u2: f32) -> Option<(Point, Vector)> {
+        None
+    }
+
+    /// Checks whether `other` is this shape or one of its descendants.
+    fn includes(&self, other: &dyn Shape) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+/// Shapes are compared by identity, so an `Intersection` can be checked
+/// against the object it was produced by.
+impl<'a> PartialEq for dyn Shape + 'a {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::Matrix;
+    use crate::point::point;
+    use crate::test_utils::*;
+    use crate::transforms::{rotation_z, scaling, translation};
+    use std::cell::Cell;
+    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+    #[derive(Debug)]
+    struct TestShape {
+        transform: Transformation,
+        saved_ray: Cell<Option<Ray>>,
+    }
+
+    impl TestShape {
+        fn new(transform: Matrix<4, 4>) -> TestShape {
+            TestShape {
+                transform: Transformation::new(transform).unwrap(),
+                saved_ray: Cell::new(None),
+            }
+        }
+    }
+
+    impl Shape for TestShape {
+        fn transform(&self) -> &Transformation {
+            &self.transform
+        }
+
+        fn material(&self) -> Material {
+            Material::new()
+        }
+
+        fn set_parent_transform(&mut self, parent: &Transformation) {
+            self.transform.set_parent(parent);
+        }
+
+        fn local_intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
+            self.saved_ray.set(Some(*r));
+            vec![]
+        }
+
+        fn bounds(&self) -> Bounds {
+            Bounds::new(point(-1, -1, -1), point(1, 1, 1))
+        }
+
+        fn local_normal_at(&self, p: Point) -> Vector {
+            p - point(0, 0, 0)
+        }
+    }
+
+    #[test]
+    fn test_intersect_scaled_shape() {
+        let s = TestShape::new(scaling(2., 2., 2.));
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        s.intersect(&r);
+        let saved_ray = s.saved_ray.get().unwrap();
+        assert_eq!(saved_ray.origin, point(0., 0., -2.5));
+        assert_eq!(saved_ray.direction, vector(0., 0., 0.5));
+    }
+
+    #[test]
+    fn test_intersect_translated_shape() {
+        let s = TestShape::new(translation(5., 0., 0.));
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        s.intersect(&r);
+        let saved_ray = s.saved_ray.get().unwrap();
+        assert_eq!(saved_ray.origin, point(-5, 0, -5));
+        assert_eq!(saved_ray.direction, vector(0, 0, 1));
+    }
+
+    #[test]
+    fn test_normal_of_translated_shape() {
+        let s = TestShape::new(translation(0., 1., 0.));
+        let n = s.normal_at(point(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+        assert_near!(n, vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+    }
+
+    #[test]
+    fn test_normal_of_transformed_shape() {
+        let s = TestShape::new(&scaling(1.0, 0.5, 1.0) * &rotation_z(PI / 5.0));
+        let n = s.normal_at(point(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+        assert_near!(n, vector(0.0, 0.97014, -0.24254));
+    }
+
+    #[test]
+    fn test_shapes_compare_by_identity() {
+        let a: Box<dyn Shape> = Box::new(TestShape::new(Matrix::identity()));
+        let b: Box<dyn Shape> = Box::new(TestShape::new(Matrix::identity()));
+        assert_eq!(a.as_ref(), a.as_ref());
+        assert_ne!(a.as_ref(), b.as_ref());
+    }
+
+    #[test]
+    fn test_parent_space_bounds() {
+        let s = TestShape::new(&translation(1., -3., 5.) * &scaling(0.5, 2., 4.));
+        assert_eq!(
+            s.parent_space_bounds(),
+            Bounds::new(point(0.5, -5., 1.), point(1.5, -1., 9.))
+        );
+    }
+}
@@ -1,34 +1,54 @@
+use crate::approx::EPSILON;
+use crate::bounds::Bounds;
+use crate::intersect::Intersection;
 use crate::material::Material;
 use crate::matrix::Matrix;
 use crate::point::{point, Point};
 use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::transformation::Transformation;
 use crate::transforms::Transform;
 use crate::vector::{dot, vector, Vector};
+use std::f32::consts::PI;
 
 #[derive(Debug, PartialEq)]
 pub struct Sphere {
-    transform: Matrix<4, 4>,
+    transform: Transformation,
     material: Material,
 }
 
 impl Sphere {
     pub fn new() -> Sphere {
         Sphere {
-            transform: Matrix::identity(),
+            transform: Transformation::identity(),
             material: Material::new(),
         }
     }
 
-    pub fn set_transform(&mut self, transform: Matrix<4, 4>) {
-        self.transform = transform;
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
     }
 
     pub fn set_material(&mut self, material: Material) {
         self.material = material;
     }
+}
+
+impl Shape for Sphere {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Transformation) {
+        self.transform.set_parent(parent);
+    }
 
-    pub fn intersect(&self, r: &Ray) -> Vec<f32> {
-        let r = r.apply(&self.transform.inverse().unwrap());
+    fn local_intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
         let origin = r.origin - point(0, 0, 0);
         let direction = r.direction;
 
@@ -40,30 +60,51 @@ impl Sphere {
         if d.is_nan() {
             vec![]
         } else {
-            vec![(-b - d) / a, (-b + d) / a]
+            vec![
+                Intersection::new(self, (-b - d) / a),
+                Intersection::new(self, (-b + d) / a),
+            ]
         }
     }
 
-    pub fn normal_at(&self, world_point: Point) -> Vector {
-        let object_point = world_point.apply(&self.transform.inverse().unwrap());
-        let object_normal = object_point - point(0., 0., 0.);
-        let world_normal = crate::tuple::Tuple::from(object_normal)
-            .apply(&self.transform.inverse().unwrap().transpose());
-        vector(world_normal.x, world_normal.y, world_normal.z).normalized()
+    fn bounds(&self) -> Bounds {
+        Bounds::new(point(-1, -1, -1), point(1, 1, 1))
+    }
+
+    fn local_normal_at(&self, object_point: Point) -> Vector {
+        object_point - point(0, 0, 0)
+    }
+
+    /// Only for spheres that stay spheres in world space, that is scaled
+    /// uniformly and not sheared.
+    fn area(&self) -> Option<f32> {
+        let m = self.transform().world();
+        let axes = [vector(1, 0, 0), vector(0, 1, 0), vector(0, 0, 1)].map(|v| v.apply(m));
+        let r = axes[0].len();
+        let is_round = axes.iter().all(|a| (a.len() - r).abs() < EPSILON)
+            && dot(axes[0], axes[1]).abs() < EPSILON
+            && dot(axes[1], axes[2]).abs() < EPSILON
+            && dot(axes[2], axes[0]).abs() < EPSILON;
+        is_round.then_some(4.0 * PI * r * r)
     }
 
-    pub fn material(&self) -> Material {
-        self.material
+    fn sample_surface(&self, u1: f32, u2: f32) -> Option<(Point, Vector)> {
+        self.area()?;
+        let z = 1.0 - 2.0 * u1;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+        let object_point = point(r * phi.cos(), r * phi.sin(), z);
+        Some((
+            object_point.apply(self.transform().world()),
+            self.normal_to_world(object_point - point(0, 0, 0)),
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::matrix::Matrix;
     use crate::point::point;
-    use crate::point::Point;
-    use crate::ray::Ray;
     use crate::test_utils::*;
     use crate::transforms::{rotation_z, scaling, translation};
     use crate::vector::{vector, Vector};
@@ -73,65 +114,82 @@ mod tests {
     fn test_ray_intersects_sphere_at_two_points() {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
         let s = Sphere::new();
-        assert_eq!(s.intersect(&r), vec![4.0, 6.0]);
+        assert_eq!(ts(s.intersect(&r)), vec![4.0, 6.0]);
+    }
+
+    #[test]
+    fn test_intersect_sets_object() {
+        let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
+        let s: Box<dyn Shape> = Box::new(Sphere::new());
+        let xs = s.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].object, s.as_ref());
+        assert_eq!(xs[1].object, s.as_ref());
     }
 
     #[test]
     fn test_sphere_behind_ray() {
         let r = Ray::new(Point::new(0., 0., 5.), Vector::new(0., 0., 1.));
         let s = Sphere::new();
-        assert_eq!(s.intersect(&r), vec![-6.0, -4.0]);
+        assert_eq!(ts(s.intersect(&r)), vec![-6.0, -4.0]);
     }
 
     #[test]
     fn test_intersect_at_point() {
         let r = Ray::new(Point::new(1., 5., 0.), Vector::new(0., 1., 0.));
         let s = Sphere::new();
-        assert_eq!(s.intersect(&r), vec![-5.0, -5.0]);
+        assert_eq!(ts(s.intersect(&r)), vec![-5.0, -5.0]);
     }
 
     #[test]
     fn test_close_miss() {
         let r = Ray::new(Point::new(1.00001, 5., 0.), Vector::new(0., 1., 0.));
         let s = Sphere::new();
-        assert_eq!(s.intersect(&r), vec![]);
+        assert_eq!(ts(s.intersect(&r)), vec![]);
     }
 
     #[test]
     fn test_clear_miss() {
         let r = Ray::new(Point::new(2., 2., 2.), Vector::new(0., 0., 1.));
         let s = Sphere::new();
-        assert_eq!(s.intersect(&r), vec![]);
+        assert_eq!(ts(s.intersect(&r)), vec![]);
     }
 
     #[test]
     fn test_default_transform() {
         let s = Sphere::new();
-        assert_eq!(s.transform, Matrix::<4, 4>::identity());
+        assert_eq!(s.transform.matrix(), &Matrix::<4, 4>::identity());
     }
 
     #[test]
     fn test_change_transform() {
         let mut s = Sphere::new();
         let t = translation(2., 3., 4.);
-        s.set_transform(t.clone());
-        assert_eq!(s.transform, t);
+        s.set_transform(t.clone()).unwrap();
+        assert_eq!(s.transform.matrix(), &t);
+    }
+
+    #[test]
+    fn test_singular_transform() {
+        let mut s = Sphere::new();
+        assert!(s.set_transform(scaling(0., 1., 1.)).is_err());
+        assert_eq!(s.transform.matrix(), &Matrix::<4, 4>::identity());
     }
 
     #[test]
     fn test_intersect_scaled_sphere() {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
         let mut s = Sphere::new();
-        s.set_transform(scaling(2., 2., 2.));
-        assert_eq!(s.intersect(&r), vec![3.0, 7.0]);
+        s.set_transform(scaling(2., 2., 2.)).unwrap();
+        assert_eq!(ts(s.intersect(&r)), vec![3.0, 7.0]);
     }
 
     #[test]
     fn test_intersect_translated_sphere() {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
         let mut s = Sphere::new();
-        s.set_transform(translation(5., 0., 0.));
-        assert_eq!(s.intersect(&r), vec![]);
+        s.set_transform(translation(5., 0., 0.)).unwrap();
+        assert_eq!(ts(s.intersect(&r)), vec![]);
     }
 
     #[test]
@@ -151,7 +209,7 @@ mod tests {
     #[test]
     fn test_normal_of_translated_sphere() {
         let mut s = Sphere::new();
-        s.set_transform(translation(0., 1., 0.));
+        s.set_transform(translation(0., 1., 0.)).unwrap();
         let n = s.normal_at(point(0.0, 1.70711, -0.70711));
         assert_near!(n, vector(0.0, 0.70711, -0.70711));
     }
@@ -159,7 +217,8 @@ mod tests {
     #[test]
     fn test_normal_of_transformedsphere() {
         let mut s = Sphere::new();
-        s.set_transform(&scaling(1.0, 0.5, 1.0) * &rotation_z(PI / 5.0));
+        s.set_transform(&scaling(1.0, 0.5, 1.0) * &rotation_z(PI / 5.0))
+            .unwrap();
         let n = s.normal_at(point(0.0, SQRT_2 / 2.0, -SQRT_2 / 2.0));
         assert_near!(n, vector(0.0, 0.97014, -0.24254));
     }
@@ -174,7 +233,40 @@ mod tests {
         let mut s = Sphere::new();
         let mut m = Material::new();
         m.ambient = 1.0;
-        s.set_material(m);
+        s.set_material(m.clone());
         assert_eq!(s.material(), m);
     }
+
+    #[test]
+    fn test_sphere_bounds() {
+        assert_eq!(
+            Sphere::new().bounds(),
+            Bounds::new(point(-1, -1, -1), point(1, 1, 1))
+        );
+    }
+
+    #[test]
+    fn test_sphere_area() {
+        let mut s = Sphere::new();
+        assert_near!(s.area().unwrap(), 4.0 * PI);
+        s.set_transform(&translation(1.0, 2.0, 3.0) * &scaling(2.0, 2.0, 2.0))
+            .unwrap();
+        assert_near!(s.area().unwrap(), 16.0 * PI, 1e-4);
+        s.set_transform(scaling(1.0, 0.5, 1.0)).unwrap();
+        assert_eq!(s.area(), None);
+        assert_eq!(s.sample_surface(0.5, 0.5), None);
+    }
+
+    #[test]
+    fn test_sample_sphere_surface() {
+        let mut s = Sphere::new();
+        s.set_transform(&translation(1.0, 2.0, 3.0) * &scaling(2.0, 2.0, 2.0))
+            .unwrap();
+        for (u1, u2) in [(0.0, 0.0), (0.25, 0.5), (0.5, 0.1), (0.99, 0.7)] {
+            let (p, n) = s.sample_surface(u1, u2).unwrap();
+            let radial = p - point(1, 2, 3);
+            assert_near!(radial.len(), 2.0, 1e-4);
+            assert_near!(n, radial.normalized(), 1e-4);
+        }
+    }
 }
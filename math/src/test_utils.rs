@@ -1,6 +1,7 @@
 pub use crate::approx::Approx;
 pub use crate::assert_near;
 
+use crate::intersect::Intersection;
 use crate::matrix::Matrix;
 
 #[macro_export]
@@ -22,3 +23,7 @@ pub fn matrix<const N: usize, const M: usize>(data: [[i16; M]; N]) -> Matrix<N,
     }
     Matrix::from_vec(vec)
 }
+
+pub fn ts(xs: Vec<Intersection>) -> Vec<f32> {
+    xs.iter().map(|x| x.t).collect()
+}
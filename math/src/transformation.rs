@@ -0,0 +1,124 @@
+use crate::matrix::Matrix;
+
+/// A transform matrix together with its inverse and inverse transpose.
+///
+/// Both derived matrices are computed once on construction, so shapes and
+/// cameras can convert rays and normals without re-inverting per ray.
+///
+/// `matrix` and `inverse` map between object and parent space. The `world_*`
+/// matrices additionally include every parent transform, and are updated by
+/// `set_parent` when the owner is placed into a `Group`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Transformation {
+    matrix: Matrix<4, 4>,
+    inverse: Matrix<4, 4>,
+    world: Matrix<4, 4>,
+    world_inverse: Matrix<4, 4>,
+    world_inverse_transpose: Matrix<4, 4>,
+}
+
+impl Transformation {
+    pub fn new(matrix: Matrix<4, 4>) -> Result<Transformation, &'static str> {
+        let inverse = matrix
+            .inverse()
+            .ok_or("Transform matrix is not invertible")?;
+        let inverse_transpose = inverse.transpose();
+        Ok(Transformation {
+            world: matrix.clone(),
+            world_inverse: inverse.clone(),
+            world_inverse_transpose: inverse_transpose,
+            matrix,
+            inverse,
+        })
+    }
+
+    pub fn identity() -> Transformation {
+        Transformation {
+            matrix: Matrix::identity(),
+            inverse: Matrix::identity(),
+            world: Matrix::identity(),
+            world_inverse: Matrix::identity(),
+            world_inverse_transpose: Matrix::identity(),
+        }
+    }
+
+    pub fn matrix(&self) -> &Matrix<4, 4> {
+        &self.matrix
+    }
+
+    pub fn inverse(&self) -> &Matrix<4, 4> {
+        &self.inverse
+    }
+
+    pub fn world(&self) -> &Matrix<4, 4> {
+        &self.world
+    }
+
+    pub fn world_inverse(&self) -> &Matrix<4, 4> {
+        &self.world_inverse
+    }
+
+    pub fn world_inverse_transpose(&self) -> &Matrix<4, 4> {
+        &self.world_inverse_transpose
+    }
+
+    /// Places the owner into the space described by `parent`.
+    pub fn set_parent(&mut self, parent: &Transformation) {
+        self.world = &parent.world * &self.matrix;
+        self.world_inverse = &self.inverse * &parent.world_inverse;
+        self.world_inverse_transpose = self.world_inverse.transpose();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use crate::transforms::{scaling, translation};
+
+    #[test]
+    fn test_identity_transformation() {
+        let t = Transformation::identity();
+        assert_eq!(t.matrix(), &Matrix::identity());
+        assert_eq!(t.inverse(), &Matrix::identity());
+        assert_eq!(t.world(), &Matrix::identity());
+        assert_eq!(t.world_inverse(), &Matrix::identity());
+        assert_eq!(t.world_inverse_transpose(), &Matrix::identity());
+    }
+
+    #[test]
+    fn test_transformation_caches_inverse() {
+        let m = &translation(1., 2., 3.) * &scaling(2., 4., 8.);
+        let t = Transformation::new(m.clone()).unwrap();
+        assert_eq!(t.matrix(), &m);
+        assert_near!(*t.inverse(), m.inverse().unwrap());
+        assert_near!(*t.world_inverse(), m.inverse().unwrap());
+        assert_near!(
+            *t.world_inverse_transpose(),
+            m.inverse().unwrap().transpose()
+        );
+    }
+
+    #[test]
+    fn test_set_parent_transformation() {
+        let parent = Transformation::new(scaling(2., 2., 2.)).unwrap();
+        let mut t = Transformation::new(translation(1., 2., 3.)).unwrap();
+        t.set_parent(&parent);
+        let world = &scaling(2., 2., 2.) * &translation(1., 2., 3.);
+        assert_eq!(t.matrix(), &translation(1., 2., 3.));
+        assert_near!(*t.world(), world);
+        assert_near!(*t.world_inverse(), world.inverse().unwrap());
+        assert_near!(
+            *t.world_inverse_transpose(),
+            world.inverse().unwrap().transpose()
+        );
+    }
+
+    #[test]
+    fn test_singular_transformation() {
+        assert_eq!(
+            Transformation::new(scaling(1., 0., 1.)),
+            Err("Transform matrix is not invertible")
+        );
+    }
+}
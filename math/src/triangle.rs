@@ -0,0 +1,324 @@
+use crate::approx::EPSILON;
+use crate::bounds::Bounds;
+use crate::intersect::Intersection;
+use crate::material::Material;
+use crate::matrix::Matrix;
+use crate::point::Point;
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::transformation::Transformation;
+use crate::transforms::Transform;
+use crate::vector::{cross, dot, Vector};
+
+#[derive(Debug, PartialEq)]
+pub struct Triangle {
+    transform: Transformation,
+    material: Material,
+    p1: Point,
+    p2: Point,
+    p3: Point,
+    e1: Vector,
+    e2: Vector,
+    normal: Vector,
+}
+
+impl Triangle {
+    pub fn new(p1: Point, p2: Point, p3: Point) -> Triangle {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        Triangle {
+            transform: Transformation::identity(),
+            material: Material::new(),
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: cross(e2, e1).normalized(),
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    /// Returns `p1` and the edges from it in world space.
+    fn world_corner_and_edges(&self) -> (Point, Vector, Vector) {
+        let m = self.transform.world();
+        (self.p1.apply(m), self.e1.apply(m), self.e2.apply(m))
+    }
+
+    /// Möller–Trumbore intersection, returns `(t, u, v)`.
+    fn intersect_uv(&self, r: &Ray) -> Option<(f32, f32, f32)> {
+        let dir_cross_e2 = cross(r.direction, self.e2);
+        let det = dot(self.e1, dir_cross_e2);
+        if det.abs() < EPSILON {
+            return None;
+        }
+
+        let f = 1.0 / det;
+        let p1_to_origin = r.origin - self.p1;
+        let u = f * dot(p1_to_origin, dir_cross_e2);
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let origin_cross_e1 = cross(p1_to_origin, self.e1);
+        let v = f * dot(r.direction, origin_cross_e1);
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        Some((f * dot(self.e2, origin_cross_e1), u, v))
+    }
+}
+
+impl Shape for Triangle {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Transformation) {
+        self.transform.set_parent(parent);
+    }
+
+    fn local_intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
+        match self.intersect_uv(r) {
+            Some((t, u, v)) => vec![Intersection::with_uv(self, t, u, v)],
+            None => vec![],
+        }
+    }
+
+    fn bounds(&self) -> Bounds {
+        let mut b = Bounds::empty();
+        for p in [self.p1, self.p2, self.p3] {
+            b.add_point(p);
+        }
+        b
+    }
+
+    fn local_normal_at(&self, _object_point: Point) -> Vector {
+        self.normal
+    }
+
+    fn area(&self) -> Option<f32> {
+        let (_, e1, e2) = self.world_corner_and_edges();
+        Some(cross(e1, e2).len() / 2.0)
+    }
+
+    fn sample_surface(&self, u1: f32, u2: f32) -> Option<(Point, Vector)> {
+        let (p1, e1, e2) = self.world_corner_and_edges();
+        let s = u1.sqrt();
+        let point = p1 + e1 * (s * (1.0 - u2)) + e2 * (s * u2);
+        Some((point, self.normal_to_world(self.normal)))
+    }
+}
+
+/// A triangle with per-vertex normals interpolated across its surface.
+#[derive(Debug, PartialEq)]
+pub struct SmoothTriangle {
+    triangle: Triangle,
+    n1: Vector,
+    n2: Vector,
+    n3: Vector,
+}
+
+impl SmoothTriangle {
+    pub fn new(p1: Point, p2: Point, p3: Point, n1: Vector, n2: Vector, n3: Vector) -> Self {
+        SmoothTriangle {
+            triangle: Triangle::new(p1, p2, p3),
+            n1,
+            n2,
+            n3,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.triangle.set_transform(transform)
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.triangle.set_material(material);
+    }
+}
+
+impl Shape for SmoothTriangle {
+    fn transform(&self) -> &Transformation {
+        self.triangle.transform()
+    }
+
+    fn material(&self) -> Material {
+        self.triangle.material()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Transformation) {
+        self.triangle.set_parent_transform(parent);
+    }
+
+    fn local_intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
+        match self.triangle.intersect_uv(r) {
+            Some((t, u, v)) => vec![Intersection::with_uv(self, t, u, v)],
+            None => vec![],
+        }
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.triangle.bounds()
+    }
+
+    fn local_normal_at(&self, object_point: Point) -> Vector {
+        self.triangle.local_normal_at(object_point)
+    }
+
+    fn local_normal_at_hit(&self, _object_point: Point, hit: &Intersection) -> Vector {
+        self.n2 * hit.u + self.n3 * hit.v + self.n1 * (1.0 - hit.u - hit.v)
+    }
+
+    fn area(&self) -> Option<f32> {
+        self.triangle.area()
+    }
+
+    fn sample_surface(&self, u1: f32, u2: f32) -> Option<(Point, Vector)> {
+        self.triangle.sample_surface(u1, u2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::point;
+    use crate::test_utils::*;
+    use crate::vector::vector;
+
+    fn default_triangle() -> Triangle {
+        Triangle::new(point(0, 1, 0), point(-1, 0, 0), point(1, 0, 0))
+    }
+
+    fn default_smooth_triangle() -> SmoothTriangle {
+        SmoothTriangle::new(
+            point(0, 1, 0),
+            point(-1, 0, 0),
+            point(1, 0, 0),
+            vector(0, 1, 0),
+            vector(-1, 0, 0),
+            vector(1, 0, 0),
+        )
+    }
+
+    #[test]
+    fn test_create_triangle() {
+        let t = default_triangle();
+        assert_eq!(t.p1, point(0, 1, 0));
+        assert_eq!(t.p2, point(-1, 0, 0));
+        assert_eq!(t.p3, point(1, 0, 0));
+        assert_eq!(t.e1, vector(-1, -1, 0));
+        assert_eq!(t.e2, vector(1, -1, 0));
+        assert_eq!(t.normal, vector(0, 0, -1));
+    }
+
+    #[test]
+    fn test_normal_on_triangle() {
+        let t = default_triangle();
+        assert_eq!(t.local_normal_at(point(0.0, 0.5, 0.0)), t.normal);
+        assert_eq!(t.local_normal_at(point(-0.5, 0.75, 0.0)), t.normal);
+        assert_eq!(t.local_normal_at(point(0.5, 0.25, 0.0)), t.normal);
+    }
+
+    #[test]
+    fn test_ray_parallel_to_triangle() {
+        let t = default_triangle();
+        let r = Ray::new(point(0, -1, -2), vector(0, 1, 0));
+        assert_eq!(ts(t.local_intersect(&r)), vec![]);
+    }
+
+    #[test]
+    fn test_ray_misses_triangle_edges() {
+        let t = default_triangle();
+        for origin in [point(1, 1, -2), point(-1, 1, -2), point(0, -1, -2)] {
+            let r = Ray::new(origin, vector(0, 0, 1));
+            assert_eq!(ts(t.local_intersect(&r)), vec![]);
+        }
+    }
+
+    #[test]
+    fn test_ray_strikes_triangle() {
+        let t = default_triangle();
+        let r = Ray::new(point(0.0, 0.5, -2.0), vector(0, 0, 1));
+        assert_eq!(ts(t.local_intersect(&r)), vec![2.0]);
+    }
+
+    #[test]
+    fn test_create_smooth_triangle() {
+        let t = default_smooth_triangle();
+        assert_eq!(t.triangle.p1, point(0, 1, 0));
+        assert_eq!(t.triangle.p2, point(-1, 0, 0));
+        assert_eq!(t.triangle.p3, point(1, 0, 0));
+        assert_eq!(t.n1, vector(0, 1, 0));
+        assert_eq!(t.n2, vector(-1, 0, 0));
+        assert_eq!(t.n3, vector(1, 0, 0));
+    }
+
+    #[test]
+    fn test_smooth_triangle_intersection_stores_uv() {
+        let t = default_smooth_triangle();
+        let r = Ray::new(point(-0.2, 0.3, -2.0), vector(0, 0, 1));
+        let xs = t.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_near!(xs[0].u, 0.45);
+        assert_near!(xs[0].v, 0.25);
+    }
+
+    #[test]
+    fn test_smooth_triangle_interpolates_normal() {
+        let t = default_smooth_triangle();
+        let i = Intersection::with_uv(&t, 1.0, 0.45, 0.25);
+        assert_near!(
+            t.normal_at_hit(point(0, 0, 0), &i),
+            vector(-0.5547, 0.83205, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_assigned_material() {
+        let mut t = default_smooth_triangle();
+        let mut m = Material::new();
+        m.ambient = 1.0;
+        t.set_material(m.clone());
+        assert_eq!(t.material(), m);
+    }
+
+    #[test]
+    fn test_triangle_bounds() {
+        let t = Triangle::new(point(-3, 7, 2), point(6, 2, -4), point(2, -1, -1));
+        assert_eq!(t.bounds(), Bounds::new(point(-3, -1, -4), point(6, 7, 2)));
+    }
+
+    #[test]
+    fn test_triangle_area() {
+        let mut t = default_triangle();
+        assert_near!(t.area().unwrap(), 1.0);
+        t.set_transform(crate::transforms::scaling(2.0, 3.0, 1.0))
+            .unwrap();
+        assert_near!(t.area().unwrap(), 6.0);
+    }
+
+    #[test]
+    fn test_sample_triangle_surface() {
+        let t = default_triangle();
+        for (u1, u2) in [(0.0, 0.0), (0.25, 0.5), (0.5, 0.1), (0.99, 0.99)] {
+            let (p, n) = t.sample_surface(u1, u2).unwrap();
+            assert_near!(p.z, 0.0);
+            assert!(p.y >= -EPSILON && p.y <= 1.0 - p.x.abs() + EPSILON);
+            assert_eq!(n, t.normal);
+        }
+    }
+}
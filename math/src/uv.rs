@@ -0,0 +1,468 @@
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::matrix::Matrix;
+use crate::pattern::Pattern;
+use crate::point::Point;
+use crate::transformation::Transformation;
+use std::f32::consts::PI;
+use std::rc::Rc;
+
+/// A 2D texture, addressed by `u` and `v` in `[0, 1]` with `v` going up.
+pub trait UvPattern: std::fmt::Debug {
+    fn uv_pattern_at(&self, u: f32, v: f32) -> Color;
+}
+
+/// Maps a point on the surface of a unit sphere to `(u, v)`.
+pub fn spherical_map(p: Point) -> (f32, f32) {
+    let theta = p.x.atan2(p.z);
+    let radius = (p.x * p.x + p.y * p.y + p.z * p.z).sqrt();
+    let phi = (p.y / radius).acos();
+    let raw_u = theta / (2.0 * PI);
+    (1.0 - (raw_u + 0.5), 1.0 - phi / PI)
+}
+
+/// Maps the xz plane to `(u, v)`, repeating every unit.
+pub fn planar_map(p: Point) -> (f32, f32) {
+    (p.x.rem_euclid(1.0), p.z.rem_euclid(1.0))
+}
+
+/// Maps a point on the surface of a unit cylinder around the y axis to
+/// `(u, v)`, repeating every unit in y.
+pub fn cylindrical_map(p: Point) -> (f32, f32) {
+    let theta = p.x.atan2(p.z);
+    let raw_u = theta / (2.0 * PI);
+    (1.0 - (raw_u + 0.5), p.y.rem_euclid(1.0))
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum UvMapping {
+    Spherical,
+    Planar,
+    Cylindrical,
+}
+
+impl UvMapping {
+    pub fn map(self, p: Point) -> (f32, f32) {
+        match self {
+            UvMapping::Spherical => spherical_map(p),
+            UvMapping::Planar => planar_map(p),
+            UvMapping::Cylindrical => cylindrical_map(p),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CubeFace {
+    Left,
+    Front,
+    Right,
+    Back,
+    Up,
+    Down,
+}
+
+impl CubeFace {
+    /// Returns the face of the unit cube the point is closest to.
+    pub fn from_point(p: Point) -> CubeFace {
+        let coord = p.x.abs().max(p.y.abs()).max(p.z.abs());
+        if coord == p.x {
+            CubeFace::Right
+        } else if coord == -p.x {
+            CubeFace::Left
+        } else if coord == p.y {
+            CubeFace::Up
+        } else if coord == -p.y {
+            CubeFace::Down
+        } else if coord == p.z {
+            CubeFace::Front
+        } else {
+            CubeFace::Back
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Maps a point on the surface of the unit cube to its face and the `(u, v)`
+/// within that face.
+pub fn cube_map(p: Point) -> (CubeFace, f32, f32) {
+    let wrap = |x: f32| x.rem_euclid(2.0) / 2.0;
+    let face = CubeFace::from_point(p);
+    let (u, v) = match face {
+        CubeFace::Front => (wrap(p.x + 1.0), wrap(p.y + 1.0)),
+        CubeFace::Back => (wrap(1.0 - p.x), wrap(p.y + 1.0)),
+        CubeFace::Left => (wrap(p.z + 1.0), wrap(p.y + 1.0)),
+        CubeFace::Right => (wrap(1.0 - p.z), wrap(p.y + 1.0)),
+        CubeFace::Up => (wrap(p.x + 1.0), wrap(1.0 - p.z)),
+        CubeFace::Down => (wrap(p.x + 1.0), wrap(p.z + 1.0)),
+    };
+    (face, u, v)
+}
+
+/// A grid of `width` by `height` alternating squares.
+#[derive(Debug, PartialEq)]
+pub struct UvCheckers {
+    pub width: f32,
+    pub height: f32,
+    pub a: Color,
+    pub b: Color,
+}
+
+impl UvCheckers {
+    pub fn new(width: f32, height: f32, a: Color, b: Color) -> UvCheckers {
+        UvCheckers {
+            width,
+            height,
+            a,
+            b,
+        }
+    }
+}
+
+impl UvPattern for UvCheckers {
+    fn uv_pattern_at(&self, u: f32, v: f32) -> Color {
+        let u2 = (u * self.width).floor();
+        let v2 = (v * self.height).floor();
+        if (u2 + v2).rem_euclid(2.0) == 0.0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+/// An image sampled with bilinear filtering.
+#[derive(Debug, PartialEq)]
+pub struct UvImage {
+    canvas: Canvas,
+}
+
+impl UvImage {
+    pub fn new(canvas: Canvas) -> Result<UvImage, &'static str> {
+        if canvas.width == 0 || canvas.height == 0 {
+            return Err("Image has no pixels");
+        }
+        Ok(UvImage { canvas })
+    }
+
+    /// Loads an image file, e.g. a PNG, through the `image` crate. Pixels
+    /// are scaled to `[0, 1]` but kept in the encoding of the file, the same
+    /// one `Image` writes, so that a texture seen directly keeps its colors.
+    pub fn read(filename: &str) -> Result<UvImage, String> {
+        let img = image::open(filename)
+            .map_err(|e| format!("{}: {}", filename, e))?
+            .to_rgb8();
+        let mut canvas = Canvas::new(img.width() as usize, img.height() as usize, Color::black());
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let [r, g, b] = pixel.0.map(|c| c as f32 / 255.0);
+            canvas.set(x as usize, y as usize, Color::new(r, g, b));
+        }
+        UvImage::new(canvas).map_err(|e| format!("{}: {}", filename, e))
+    }
+}
+
+impl UvPattern for UvImage {
+    fn uv_pattern_at(&self, u: f32, v: f32) -> Color {
+        let (w, h) = (self.canvas.width, self.canvas.height);
+        // Pixel centers are at whole coordinates, (0, 0) is the top left.
+        let x = (u.clamp(0.0, 1.0) * (w - 1) as f32).max(0.0);
+        let y = ((1.0 - v.clamp(0.0, 1.0)) * (h - 1) as f32).max(0.0);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let top = self.canvas.get(x0, y0) * (1.0 - fx) + self.canvas.get(x1, y0) * fx;
+        let bottom = self.canvas.get(x0, y1) * (1.0 - fx) + self.canvas.get(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+/// Wraps a `UvPattern` around a shape with one of the `UvMapping`s.
+#[derive(Debug)]
+pub struct TextureMapPattern {
+    transform: Transformation,
+    pub uv_pattern: Rc<dyn UvPattern>,
+    pub mapping: UvMapping,
+}
+
+impl TextureMapPattern {
+    pub fn new(uv_pattern: Rc<dyn UvPattern>, mapping: UvMapping) -> TextureMapPattern {
+        TextureMapPattern {
+            transform: Transformation::identity(),
+            uv_pattern,
+            mapping,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+}
+
+impl Pattern for TextureMapPattern {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn local_pattern_at(&self, p: Point) -> Color {
+        let (u, v) = self.mapping.map(p);
+        self.uv_pattern.uv_pattern_at(u, v)
+    }
+}
+
+/// Puts a separate `UvPattern` on each face of the unit cube, in the order
+/// of `CubeFace`.
+#[derive(Debug)]
+pub struct CubeMapPattern {
+    transform: Transformation,
+    pub faces: [Rc<dyn UvPattern>; 6],
+}
+
+impl CubeMapPattern {
+    pub fn new(faces: [Rc<dyn UvPattern>; 6]) -> CubeMapPattern {
+        CubeMapPattern {
+            transform: Transformation::identity(),
+            faces,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix<4, 4>) -> Result<(), &'static str> {
+        self.transform = Transformation::new(transform)?;
+        Ok(())
+    }
+}
+
+impl Pattern for CubeMapPattern {
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn local_pattern_at(&self, p: Point) -> Color {
+        let (face, u, v) = cube_map(p);
+        self.faces[face.index()].uv_pattern_at(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{Image, RGB};
+    use crate::point::point;
+    use crate::test_utils::*;
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    fn white() -> Color {
+        Color::new(1., 1., 1.)
+    }
+
+    #[test]
+    fn test_checker_pattern_in_2d() {
+        let c = UvCheckers::new(2., 2., Color::black(), white());
+        let cases = [
+            (0.0, 0.0, Color::black()),
+            (0.5, 0.0, white()),
+            (0.0, 0.5, white()),
+            (0.5, 0.5, Color::black()),
+            (1.0, 1.0, Color::black()),
+        ];
+        for (u, v, expected) in cases {
+            assert_eq!(c.uv_pattern_at(u, v), expected);
+        }
+    }
+
+    #[test]
+    fn test_spherical_mapping_on_3d_point() {
+        let cases = [
+            (point(0, 0, -1), 0.0, 0.5),
+            (point(1, 0, 0), 0.25, 0.5),
+            (point(0, 0, 1), 0.5, 0.5),
+            (point(-1, 0, 0), 0.75, 0.5),
+            (point(0, 1, 0), 0.5, 1.0),
+            (point(0, -1, 0), 0.5, 0.0),
+            (point(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0), 0.25, 0.75),
+        ];
+        for (p, u, v) in cases {
+            let (pu, pv) = spherical_map(p);
+            assert_near!(pu, u);
+            assert_near!(pv, v);
+        }
+    }
+
+    #[test]
+    fn test_planar_mapping_on_3d_point() {
+        let cases = [
+            (point(0.25, 0.0, 0.5), 0.25, 0.5),
+            (point(0.25, 0.0, -0.25), 0.25, 0.75),
+            (point(0.25, 0.5, -0.25), 0.25, 0.75),
+            (point(1.25, 0.0, 0.5), 0.25, 0.5),
+            (point(0.25, 0.0, -1.75), 0.25, 0.25),
+            (point(1.0, 0.0, -1.0), 0.0, 0.0),
+            (point(0, 0, 0), 0.0, 0.0),
+        ];
+        for (p, u, v) in cases {
+            assert_eq!(planar_map(p), (u, v));
+        }
+    }
+
+    #[test]
+    fn test_cylindrical_mapping_on_3d_point() {
+        let cases = [
+            (point(0, 0, -1), 0.0, 0.0),
+            (point(0.0, 0.5, -1.0), 0.0, 0.5),
+            (point(0, 1, -1), 0.0, 0.0),
+            (point(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2), 0.125, 0.5),
+            (point(1.0, 0.5, 0.0), 0.25, 0.5),
+            (point(FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2), 0.375, 0.5),
+            (point(0.0, -0.25, 1.0), 0.5, 0.75),
+            (point(-FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2), 0.625, 0.5),
+            (point(-1.0, 1.25, 0.0), 0.75, 0.25),
+            (point(-FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2), 0.875, 0.5),
+        ];
+        for (p, u, v) in cases {
+            let (pu, pv) = cylindrical_map(p);
+            assert_near!(pu, u);
+            assert_near!(pv, v);
+        }
+    }
+
+    #[test]
+    fn test_identify_face_of_cube_from_point() {
+        let cases = [
+            (point(-1.0, 0.5, -0.25), CubeFace::Left),
+            (point(1.1, -0.75, 0.8), CubeFace::Right),
+            (point(0.1, 0.6, 0.9), CubeFace::Front),
+            (point(-0.7, 0.0, -2.0), CubeFace::Back),
+            (point(0.5, 1.0, 0.9), CubeFace::Up),
+            (point(-0.2, -1.3, 1.1), CubeFace::Down),
+        ];
+        for (p, face) in cases {
+            assert_eq!(CubeFace::from_point(p), face);
+        }
+    }
+
+    #[test]
+    fn test_cube_mapping_on_each_face() {
+        let cases = [
+            (point(-0.5, 0.5, 1.0), CubeFace::Front, 0.25, 0.75),
+            (point(0.5, -0.5, 1.0), CubeFace::Front, 0.75, 0.25),
+            (point(0.5, 0.5, -1.0), CubeFace::Back, 0.25, 0.75),
+            (point(-0.5, -0.5, -1.0), CubeFace::Back, 0.75, 0.25),
+            (point(-1.0, 0.5, -0.5), CubeFace::Left, 0.25, 0.75),
+            (point(-1.0, -0.5, 0.5), CubeFace::Left, 0.75, 0.25),
+            (point(1.0, 0.5, 0.5), CubeFace::Right, 0.25, 0.75),
+            (point(1.0, -0.5, -0.5), CubeFace::Right, 0.75, 0.25),
+            (point(-0.5, 1.0, -0.5), CubeFace::Up, 0.25, 0.75),
+            (point(0.5, 1.0, 0.5), CubeFace::Up, 0.75, 0.25),
+            (point(-0.5, -1.0, 0.5), CubeFace::Down, 0.25, 0.75),
+            (point(0.5, -1.0, -0.5), CubeFace::Down, 0.75, 0.25),
+        ];
+        for (p, face, u, v) in cases {
+            assert_eq!(cube_map(p), (face, u, v));
+        }
+    }
+
+    #[test]
+    fn test_texture_map_pattern_with_spherical_map() {
+        let checkers = UvCheckers::new(16., 8., Color::black(), white());
+        let p = TextureMapPattern::new(Rc::new(checkers), UvMapping::Spherical);
+        let cases = [
+            (point(0.4315, 0.4670, 0.7719), white()),
+            (point(-0.9654, 0.2552, -0.0534), Color::black()),
+            (point(0.1039, 0.7090, 0.6975), white()),
+            (point(-0.4986, -0.7856, -0.3663), Color::black()),
+            (point(-0.0317, -0.9395, 0.3411), Color::black()),
+            (point(0.4809, -0.7721, 0.4154), Color::black()),
+            (point(0.0285, -0.9612, -0.2745), Color::black()),
+            (point(-0.5734, -0.2162, -0.7903), white()),
+            (point(0.7688, -0.1470, 0.6223), Color::black()),
+            (point(-0.7652, 0.2175, 0.6060), Color::black()),
+        ];
+        for (q, c) in cases {
+            assert_eq!(p.pattern_at(q), c);
+        }
+    }
+
+    #[test]
+    fn test_cube_map_pattern_picks_face() {
+        let faces: [Rc<dyn UvPattern>; 6] = [0.0, 0.2, 0.4, 0.6, 0.8, 1.0].map(|c| {
+            Rc::new(UvCheckers::new(1., 1., Color::new(c, c, c), Color::black()))
+                as Rc<dyn UvPattern>
+        });
+        let p = CubeMapPattern::new(faces);
+        assert_eq!(
+            p.pattern_at(point(1.0, 0.0, 0.0)),
+            Color::new(0.4, 0.4, 0.4)
+        );
+        assert_eq!(p.pattern_at(point(0.0, -1.0, 0.0)), white());
+    }
+
+    fn two_by_two() -> UvImage {
+        let mut canvas = Canvas::new(2, 2, Color::black());
+        canvas.set(1, 0, white());
+        canvas.set(0, 1, Color::new(1., 0., 0.));
+        UvImage::new(canvas).unwrap()
+    }
+
+    #[test]
+    fn test_image_corners_match_pixels() {
+        let img = two_by_two();
+        assert_eq!(img.uv_pattern_at(0.0, 1.0), Color::black());
+        assert_eq!(img.uv_pattern_at(1.0, 1.0), white());
+        assert_eq!(img.uv_pattern_at(0.0, 0.0), Color::new(1., 0., 0.));
+        assert_eq!(img.uv_pattern_at(1.0, 0.0), Color::black());
+    }
+
+    #[test]
+    fn test_image_is_filtered_bilinearly() {
+        let img = two_by_two();
+        assert_near!(img.uv_pattern_at(0.5, 1.0), Color::new(0.5, 0.5, 0.5));
+        assert_near!(img.uv_pattern_at(0.0, 0.5), Color::new(0.5, 0.0, 0.0));
+        assert_near!(img.uv_pattern_at(0.5, 0.5), Color::new(0.5, 0.25, 0.25));
+    }
+
+    #[test]
+    fn test_read_missing_image() {
+        assert!(UvImage::read("does/not/exist.png").is_err());
+    }
+
+    #[test]
+    fn test_read_png_image() {
+        let path = std::env::temp_dir().join("uv_test_read_png_image.png");
+        let path = path.to_str().unwrap();
+        let mut canvas = Canvas::new(3, 1, white());
+        canvas.set(1, 0, Color::new(0.5, 0.5, 0.5));
+        canvas.set(2, 0, Color::new(0., 0., 1.));
+        Image::from_canvas(canvas).write_png(path);
+        let img = UvImage::read(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(img.uv_pattern_at(0.0, 0.0), white());
+        assert_eq!(img.uv_pattern_at(1.0, 0.0), Color::new(0., 0., 1.));
+        assert_near!(
+            img.uv_pattern_at(0.5, 0.0),
+            Color::new(0.50196, 0.50196, 0.50196),
+            1e-4
+        );
+    }
+
+    #[test]
+    fn test_png_texture_round_trip() {
+        let path = std::env::temp_dir().join("uv_test_png_texture_round_trip.png");
+        let path = path.to_str().unwrap();
+        let rgb = RGB::from_u8(128, 64, 200);
+        let mut source = Image::new(1, 1, RGB::black());
+        source.set(0, 0, rgb);
+        source.write_png(path);
+        let img = UvImage::read(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let canvas = Canvas::new(1, 1, img.uv_pattern_at(0.5, 0.5));
+        assert_eq!(Image::from_canvas(canvas).get(0, 0), rgb);
+    }
+
+    #[test]
+    fn test_empty_image_is_rejected() {
+        assert!(UvImage::new(Canvas::new(0, 0, white())).is_err());
+        assert!(UvImage::new(Canvas::new(4, 0, white())).is_err());
+    }
+}
@@ -1,80 +1,202 @@
+use crate::approx::EPSILON;
+use crate::background::Background;
+use crate::bvh::Bvh;
 use crate::color::Color;
 use crate::intersect::{hit, Intersection};
-use crate::light::PointLight;
-use crate::lighting;
+use crate::light::{Light, LightSample, PointLight};
 use crate::material::Material;
 use crate::point::{point, Point};
+use crate::random::Rng;
 use crate::ray::Ray;
+use crate::sampling;
+use crate::shape::Shape;
 use crate::sphere::Sphere;
 use crate::transforms;
 use crate::vector::{dot, Vector};
+use std::cell::OnceCell;
 
-pub trait Intersect {
-    fn intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>>;
-    fn normal_at(&self, world_point: Point) -> Vector;
-    fn material_at(&self, world_point: Point) -> Material;
-}
-
-#[derive(Debug, PartialEq)]
-pub enum WorldObject {
-    Sphere(Sphere),
-}
-
-impl Intersect for WorldObject {
-    fn intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
-        let ts = match self {
-            WorldObject::Sphere(s) => s.intersect(r),
-        };
-        let mut intersections = Vec::with_capacity(ts.len());
-        for t in ts {
-            intersections.push(Intersection::new(self, t));
-        }
-        intersections
-    }
-
-    fn normal_at(&self, world_point: Point) -> Vector {
-        match self {
-            WorldObject::Sphere(s) => s.normal_at(world_point),
-        }
-    }
+/// How many times a ray may bounce off reflective or transparent surfaces.
+pub const MAX_DEPTH: usize = 5;
 
-    fn material_at(&self, _world_point: Point) -> Material {
-        match self {
-            WorldObject::Sphere(s) => s.material(),
-        }
-    }
-}
+/// How far short of a sampled point on an emissive surface a shadow ray may
+/// hit, to allow for the error in intersecting the surface itself.
+const SHADOW_TOLERANCE: f32 = 1e-3;
 
 pub struct Computations<'a> {
-    pub object: &'a WorldObject,
+    pub object: &'a dyn Shape,
     pub point: Point,
+    pub over_point: Point,
+    pub under_point: Point,
     pub eye_vec: Vector,
     pub normal_vec: Vector,
+    pub reflect_vec: Vector,
     pub is_inside: bool,
+    /// Refractive indices of the materials the ray leaves and enters.
+    pub n1: f32,
+    pub n2: f32,
 }
 
 impl<'a> Computations<'a> {
-    pub fn new(i: Intersection<'a>, r: &Ray) -> Self {
+    /// Prepares the shading of the hit `i`, `xs` are all intersections of
+    /// the ray sorted by `t` and are used to find the refractive indices.
+    pub fn new(i: Intersection<'a>, r: &Ray, xs: &[Intersection<'a>]) -> Self {
         let point = r.position(i.t);
-        let mut normal_vec = i.object.normal_at(point);
+        let mut normal_vec = i.object.normal_at_hit(point, &i);
         let eye_vec = -r.direction;
         let is_inside = dot(normal_vec, eye_vec) < 0.0;
         if is_inside {
             normal_vec = -normal_vec;
         }
+        let over_point = point + normal_vec * EPSILON;
+        let under_point = point - normal_vec * EPSILON;
+        let reflect_vec = r.direction.reflected(normal_vec);
+        let (n1, n2) = refractive_indices(i, xs);
         Self {
             object: i.object,
             point,
+            over_point,
+            under_point,
             eye_vec,
             normal_vec,
+            reflect_vec,
             is_inside,
+            n1,
+            n2,
+        }
+    }
+
+    /// Schlick's approximation of the fraction of light that is reflected.
+    pub fn schlick(&self) -> f32 {
+        let mut cos = dot(self.eye_vec, self.normal_vec);
+        if self.n1 > self.n2 {
+            let n = self.n1 / self.n2;
+            let sin2_t = n * n * (1.0 - cos * cos);
+            if sin2_t > 1.0 {
+                return 1.0;
+            }
+            cos = (1.0 - sin2_t).sqrt();
+        }
+        let r0 = ((self.n1 - self.n2) / (self.n1 + self.n2)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+    }
+
+    /// Direction of the refracted ray by Snell's law, or `None` on total
+    /// internal reflection.
+    pub fn refract_vec(&self) -> Option<Vector> {
+        let n_ratio = self.n1 / self.n2;
+        let cos_i = dot(self.eye_vec, self.normal_vec);
+        let sin2_t = n_ratio * n_ratio * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            return None;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+        Some(self.normal_vec * (n_ratio * cos_i - cos_t) - self.eye_vec * n_ratio)
+    }
+}
+
+/// Walks the intersections up to the hit, keeping track of the objects the
+/// ray is inside of.
+fn refractive_indices<'a>(hit: Intersection<'a>, xs: &[Intersection<'a>]) -> (f32, f32) {
+    let index = |containers: &[&dyn Shape]| match containers.last() {
+        Some(object) => object.material().refractive_index,
+        None => 1.0,
+    };
+    let mut containers: Vec<&dyn Shape> = vec![];
+    let mut n1 = 1.0;
+    for x in xs {
+        if *x == hit {
+            n1 = index(&containers);
         }
+        match containers.iter().position(|c| *c == x.object) {
+            Some(pos) => {
+                containers.remove(pos);
+            }
+            None => containers.push(x.object),
+        }
+        if *x == hit {
+            return (n1, index(&containers));
+        }
+    }
+    (n1, index(&containers))
+}
+
+/// The emissive shapes of a world that can be sampled as lights.
+struct Emitters {
+    /// Indices from `World::objects` down through `Shape::children` to each
+    /// shape.
+    paths: Vec<Vec<usize>>,
+    /// The same shapes, to tell whether a ray hit one of them. Only valid
+    /// while the objects are unchanged.
+    shapes: Vec<*const dyn Shape>,
+    /// Running total of the areas, for picking shapes in proportion to their
+    /// area.
+    cumulative_area: Vec<f32>,
+}
+
+impl Emitters {
+    fn find(objects: &[Box<dyn Shape>]) -> Emitters {
+        fn walk(shapes: &[Box<dyn Shape>], path: &mut Vec<usize>, emitters: &mut Emitters) {
+            for (i, shape) in shapes.iter().enumerate() {
+                path.push(i);
+                if let Some(area) = shape.area() {
+                    if shape.material().emission != Color::black() {
+                        let total = emitters.total_area();
+                        emitters.paths.push(path.clone());
+                        emitters.shapes.push(shape.as_ref());
+                        emitters.cumulative_area.push(total + area);
+                    }
+                }
+                walk(shape.children(), path, emitters);
+                path.pop();
+            }
+        }
+
+        let mut emitters = Emitters {
+            paths: vec![],
+            shapes: vec![],
+            cumulative_area: vec![],
+        };
+        walk(objects, &mut vec![], &mut emitters);
+        emitters
+    }
+
+    fn total_area(&self) -> f32 {
+        self.cumulative_area.last().copied().unwrap_or(0.0)
+    }
+
+    /// Checks whether `shape` is one of the emitters, which `pick` may return.
+    fn contains(&self, shape: &dyn Shape) -> bool {
+        self.shapes.iter().any(|s| std::ptr::addr_eq(*s, shape))
+    }
+
+    /// Picks a shape with a chance proportional to its area, `u` being
+    /// uniform in `[0, 1)`.
+    fn pick<'a>(&self, objects: &'a [Box<dyn Shape>], u: f32) -> &'a dyn Shape {
+        let target = u * self.total_area();
+        let i = self
+            .cumulative_area
+            .partition_point(|area| *area <= target)
+            .min(self.paths.len() - 1);
+        let mut shape = objects[self.paths[i][0]].as_ref();
+        for j in &self.paths[i][1..] {
+            shape = shape.children()[*j].as_ref();
+        }
+        shape
     }
 }
 
+/// A scene to render.
+///
+/// Objects are kept behind accessors so that the `Bvh` over them, built on
+/// the first intersection, and the list of emissive shapes are dropped
+/// whenever they may have changed.
 pub struct World {
-    pub objects: Vec<WorldObject>,
-    pub lights: Vec<PointLight>,
+    objects: Vec<Box<dyn Shape>>,
+    pub lights: Vec<Box<dyn Light>>,
+    /// What rays see when they miss every object, black by default.
+    pub background: Background,
+    bvh: OnceCell<Bvh>,
+    emitters: OnceCell<Emitters>,
 }
 
 impl World {
@@ -82,49 +204,250 @@ impl World {
         World {
             objects: vec![],
             lights: vec![],
+            background: Background::Color(Color::black()),
+            bvh: OnceCell::new(),
+            emitters: OnceCell::new(),
         }
     }
 
+    pub fn objects(&self) -> &[Box<dyn Shape>] {
+        &self.objects
+    }
+
+    pub fn objects_mut(&mut self) -> &mut Vec<Box<dyn Shape>> {
+        self.bvh.take();
+        self.emitters.take();
+        &mut self.objects
+    }
+
+    pub fn add_object(&mut self, object: Box<dyn Shape>) {
+        self.objects_mut().push(object);
+    }
+
+    fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| Bvh::build(&self.objects))
+    }
+
+    fn emitters(&self) -> &Emitters {
+        self.emitters.get_or_init(|| Emitters::find(&self.objects))
+    }
+
     pub fn intersect<'a>(&'a self, r: &Ray) -> Vec<Intersection<'a>> {
-        let mut xs = vec![];
-        for object in self.objects.iter() {
-            xs.extend(object.intersect(r).into_iter());
-        }
+        let mut xs = self.bvh().intersect(&self.objects, r);
         xs.sort_by(|x, y| x.t.partial_cmp(&y.t).unwrap());
         xs
     }
 
-    pub fn shade_hit(&self, comps: Computations) -> Color {
-        let mut c = Color::black();
-        for light in &self.lights {
-            c = c + lighting::phong(
-                comps.object.material_at(comps.point),
-                light,
-                comps.point,
+    /// Same as `hit(&self.intersect(r))`, but stops descending into parts of
+    /// the scene that are farther away than the closest hit found so far.
+    pub fn hit<'a>(&'a self, r: &Ray) -> Option<Intersection<'a>> {
+        self.bvh().hit(&self.objects, r)
+    }
+
+    /// Colors a hit, following reflected rays at most `remaining` times.
+    /// Lights with many samples draw them from `rng`.
+    pub fn shade_hit(&self, comps: Computations, remaining: usize, rng: &mut Rng) -> Color {
+        let material = comps.object.material_at(comps.point);
+        let surface = material.emission
+            + material.shader.shade(
+                &material,
+                &self.lights,
                 comps.eye_vec,
                 comps.normal_vec,
+                &mut |light| self.light_samples(light, comps.point, comps.over_point, rng),
             );
+        let reflected = self.reflected_color(&comps, remaining, rng);
+        let refracted = self.refracted_color(&comps, remaining, rng);
+        if material.reflective > 0.0 && material.transparency > 0.0 {
+            let reflectance = comps.schlick();
+            surface + reflectance * reflected + (1.0 - reflectance) * refracted
+        } else {
+            surface + reflected + refracted
+        }
+    }
+
+    /// Estimates the light of `lights` and of emissive objects reflected
+    /// towards the eye at a hit, for Monte Carlo renderers.
+    ///
+    /// Each light is sampled once at a random point with a shadow ray, and so
+    /// is one emissive object, picked in proportion to its area. Lights and
+    /// objects with an area are also looked for in a direction sampled from
+    /// the BSDF, which finds large lights more reliably, and the two
+    /// estimates are combined with the power heuristic.
+    ///
+    /// Emissive objects without an area, and so not sampled directly, are
+    /// only found by the BSDF sample. Renderers should not add the emission
+    /// of objects hit after a diffuse bounce, since it is included here.
+    pub fn direct_light(&self, comps: &Computations, rng: &mut Rng) -> Color {
+        let m = comps.object.material_at(comps.point);
+        let (p, v, n) = (comps.over_point, comps.eye_vec, comps.normal_vec);
+        let mut sum = Color::black();
+        for light in &self.lights {
+            let (sample, light_pdf) = light.sample(p, rng.next_f32(), rng.next_f32());
+            let cos = dot(sample.direction, n);
+            if cos > 0.0 && !self.is_shadowed(&sample, p) {
+                let f = m.shader.bsdf(&m, sample.direction, v, n) * sample.intensity;
+                let weight = match light_pdf {
+                    Some(light_pdf) => {
+                        let bsdf_pdf = m.shader.pdf(&m, sample.direction, v, n);
+                        sampling::power_heuristic(light_pdf, bsdf_pdf) / light_pdf
+                    }
+                    None => 1.0,
+                };
+                sum = sum + (weight * cos) * f;
+            }
+
+            let direction = m
+                .shader
+                .sample_direction(&m, v, n, rng.next_f32(), rng.next_f32());
+            if let Some((sample, light_pdf)) = light.hit(p, direction) {
+                let cos = dot(direction, n);
+                let bsdf_pdf = m.shader.pdf(&m, direction, v, n);
+                if cos > 0.0 && bsdf_pdf > 0.0 && !self.is_shadowed(&sample, p) {
+                    let f = m.shader.bsdf(&m, direction, v, n) * sample.intensity;
+                    let weight = sampling::power_heuristic(bsdf_pdf, light_pdf) / bsdf_pdf;
+                    sum = sum + (weight * cos) * f;
+                }
+            }
         }
-        c
+        sum + self.emitted_light(&m, comps, rng)
     }
 
-    pub fn color_at(&self, r: &Ray) -> Color {
+    /// The part of `direct_light` coming from emissive objects.
+    fn emitted_light(&self, m: &Material, comps: &Computations, rng: &mut Rng) -> Color {
+        let (p, v, n) = (comps.over_point, comps.eye_vec, comps.normal_vec);
+        let emitters = self.emitters();
+        let total_area = emitters.total_area();
+        let mut sum = Color::black();
+        // Density over solid angle of picking a point seen at `distance`
+        // along `direction` on a surface with normal `normal`.
+        let light_pdf = |direction: Vector, distance: f32, normal: Vector| {
+            distance * distance / (total_area * dot(normal, direction).abs())
+        };
+
+        if total_area > 0.0 {
+            let shape = emitters.pick(&self.objects, rng.next_f32());
+            if let Some((position, normal)) = shape.sample_surface(rng.next_f32(), rng.next_f32()) {
+                let to_light = position - p;
+                let distance = to_light.len();
+                let direction = to_light / distance;
+                let cos = dot(direction, n);
+                // The point must be the first thing the ray meets, which
+                // also leaves out points of a `Csg` child that are not on the
+                // surface of the combined shape.
+                let visible = match self.hit(&Ray::new(p, direction)) {
+                    Some(h) => (h.t - distance).abs() < SHADOW_TOLERANCE,
+                    None => false,
+                };
+                if cos > 0.0 && dot(normal, direction) != 0.0 && visible {
+                    let light_pdf = light_pdf(direction, distance, normal);
+                    let bsdf_pdf = m.shader.pdf(m, direction, v, n);
+                    let emission = shape.material_at(position).emission;
+                    let f = m.shader.bsdf(m, direction, v, n) * emission;
+                    let weight = sampling::power_heuristic(light_pdf, bsdf_pdf) / light_pdf;
+                    sum = sum + (weight * cos) * f;
+                }
+            }
+        }
+
+        let direction = m
+            .shader
+            .sample_direction(m, v, n, rng.next_f32(), rng.next_f32());
+        let cos = dot(direction, n);
+        let bsdf_pdf = m.shader.pdf(m, direction, v, n);
+        if cos <= 0.0 || bsdf_pdf <= 0.0 {
+            return sum;
+        }
+        let r = Ray::new(p, direction);
+        if let Some(h) = self.hit(&r) {
+            let position = r.position(h.t);
+            let emission = h.object.material_at(position).emission;
+            if emission != Color::black() {
+                // Emissive shapes that are never picked, like those without
+                // an area, are only found by this ray.
+                let weight = if emitters.contains(h.object) {
+                    let normal = h.object.normal_at_hit(position, &h);
+                    let light_pdf = light_pdf(direction, h.t, normal);
+                    sampling::power_heuristic(bsdf_pdf, light_pdf) / bsdf_pdf
+                } else {
+                    1.0 / bsdf_pdf
+                };
+                let f = m.shader.bsdf(m, direction, v, n) * emission;
+                sum = sum + (weight * cos) * f;
+            }
+        }
+        sum
+    }
+
+    pub fn reflected_color(&self, comps: &Computations, remaining: usize, rng: &mut Rng) -> Color {
+        let reflective = comps.object.material_at(comps.point).reflective;
+        if remaining == 0 || reflective == 0.0 {
+            return Color::black();
+        }
+        let r = Ray::new(comps.over_point, comps.reflect_vec);
+        reflective * self.color_at(&r, remaining - 1, rng)
+    }
+
+    pub fn refracted_color(&self, comps: &Computations, remaining: usize, rng: &mut Rng) -> Color {
+        let transparency = comps.object.material_at(comps.point).transparency;
+        if remaining == 0 || transparency == 0.0 {
+            return Color::black();
+        }
+        match comps.refract_vec() {
+            Some(direction) => {
+                let r = Ray::new(comps.under_point, direction);
+                transparency * self.color_at(&r, remaining - 1, rng)
+            }
+            None => Color::black(),
+        }
+    }
+
+    /// Returns the samples of the light at the point, with those hidden by
+    /// an object as seen from `over_point` turned black. Shading uses these,
+    /// so each sample is both lit from and tested for shadows.
+    pub fn light_samples(
+        &self,
+        light: &dyn Light,
+        point: Point,
+        over_point: Point,
+        rng: &mut Rng,
+    ) -> Vec<LightSample> {
+        let mut samples = light.samples_at(point, rng);
+        for sample in &mut samples {
+            if self.is_shadowed(sample, over_point) {
+                sample.intensity = Color::black();
+            }
+        }
+        samples
+    }
+
+    /// Returns whether something lies between the point and the sample on
+    /// the light.
+    pub fn is_shadowed(&self, sample: &LightSample, point: Point) -> bool {
+        let r = Ray::new(point, sample.direction);
+        match self.hit(&r) {
+            Some(h) => h.t < sample.distance,
+            None => false,
+        }
+    }
+
+    pub fn color_at(&self, r: &Ray, remaining: usize, rng: &mut Rng) -> Color {
         let xs = self.intersect(r);
         if let Some(x) = hit(&xs) {
-            self.shade_hit(Computations::new(x, r))
+            self.shade_hit(Computations::new(x, r, &xs), remaining, rng)
         } else {
-            Color::black()
+            self.background.color_for(r.direction)
         }
     }
 }
 
 pub fn default_world() -> World {
     let mut w = World::new();
-    w.lights = vec![PointLight::new(
+    w.lights = vec![Box::new(PointLight::new(
         point(-10., -10., -10.),
         Color::new(1., 1., 1.),
-    )];
-    w.objects.push(WorldObject::Sphere({
+    ))];
+    w.add_object(Box::new({
         let mut s = Sphere::new();
         s.set_material({
             let mut m = Material::new();
@@ -135,9 +458,9 @@ pub fn default_world() -> World {
         });
         s
     }));
-    w.objects.push(WorldObject::Sphere({
+    w.add_object(Box::new({
         let mut s = Sphere::new();
-        s.set_transform(transforms::scaling(0.5, 0.5, 0.5));
+        s.set_transform(transforms::scaling(0.5, 0.5, 0.5)).unwrap();
         s
     }));
     w
@@ -146,45 +469,58 @@ pub fn default_world() -> World {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::background::{Background, EnvironmentMap};
+    use crate::canvas::Canvas;
     use crate::color::Color;
-    use crate::light::PointLight;
+    use crate::csg::{Csg, CsgOperation};
+    use crate::group::Group;
+    use crate::intersect::hit;
+    use crate::light::{AreaLight, DirectionalLight, EnvironmentLight, PointLight, SpotLight};
+    use crate::lighting;
     use crate::material::Material;
+    use crate::matrix::Matrix;
+    use crate::plane::Plane;
     use crate::point::{point, Point};
     use crate::ray::Ray;
     use crate::sphere::Sphere;
     use crate::test_utils::*;
     use crate::transforms;
+    use crate::triangle::Triangle;
     use crate::vector::{vector, Vector};
+    use std::f32::consts::{FRAC_1_SQRT_2, PI, SQRT_2};
 
     #[test]
     fn test_create_world() {
         let w = World::new();
-        assert_eq!(w.objects, vec![]);
-        assert_eq!(w.lights, vec![]);
+        assert!(w.objects().is_empty());
+        assert!(w.lights.is_empty());
     }
 
     #[test]
     fn test_create_default_world() {
         let w = default_world();
-        let s1 = WorldObject::Sphere({
-            let mut s = Sphere::new();
-            s.set_material({
-                let mut m = Material::new();
-                m.color = Color::new(0.8, 1.0, 0.6);
-                m.diffuse = 0.7;
-                m.specular = 0.2;
-                m
-            });
-            s
-        });
-        let s2 = WorldObject::Sphere({
-            let mut s = Sphere::new();
-            s.set_transform(transforms::scaling(0.5, 0.5, 0.5));
-            s
-        });
         let light = PointLight::new(point(-10, -10, -10), Color::new(1., 1., 1.));
-        assert_eq!(w.lights, vec![light]);
-        assert_eq!(w.objects, vec![s1, s2]);
+        assert_eq!(w.lights.len(), 1);
+        assert_eq!(w.lights[0].intensity(), light.intensity);
+        let p = point(1, 2, 3);
+        assert_eq!(
+            w.lights[0].samples_at(p, &mut Rng::new(0)),
+            light.samples_at(p, &mut Rng::new(0))
+        );
+        assert_eq!(w.objects().len(), 2);
+        assert_eq!(w.objects()[0].transform().matrix(), &Matrix::identity());
+        assert_eq!(w.objects()[0].material(), {
+            let mut m = Material::new();
+            m.color = Color::new(0.8, 1.0, 0.6);
+            m.diffuse = 0.7;
+            m.specular = 0.2;
+            m
+        });
+        assert_eq!(
+            w.objects()[1].transform().matrix(),
+            &transforms::scaling(0.5, 0.5, 0.5)
+        );
+        assert_eq!(w.objects()[1].material(), Material::new());
     }
 
     #[test]
@@ -202,10 +538,10 @@ mod tests {
     #[test]
     fn test_intersection_outside() {
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
-        let s = WorldObject::Sphere(Sphere::new());
+        let s = Sphere::new();
         let i = Intersection::new(&s, 4.);
-        let comps = Computations::new(i, &r);
-        assert_eq!(comps.object, &s);
+        let comps = Computations::new(i, &r, &[i]);
+        assert_eq!(comps.object, &s as &dyn Shape);
         assert_eq!(comps.point, point(0, 0, -1));
         assert_eq!(comps.eye_vec, vector(0, 0, -1));
         assert_eq!(comps.normal_vec, vector(0, 0, -1));
@@ -215,10 +551,10 @@ mod tests {
     #[test]
     fn test_intersection_inside() {
         let r = Ray::new(Point::new(0., 0., 0.), Vector::new(0., 0., 1.));
-        let s = WorldObject::Sphere(Sphere::new());
+        let s = Sphere::new();
         let i = Intersection::new(&s, 1.);
-        let comps = Computations::new(i, &r);
-        assert_eq!(comps.object, &s);
+        let comps = Computations::new(i, &r, &[i]);
+        assert_eq!(comps.object, &s as &dyn Shape);
         assert_eq!(comps.point, point(0, 0, 1));
         assert_eq!(comps.eye_vec, vector(0, 0, -1));
         assert_eq!(comps.normal_vec, vector(0, 0, -1));
@@ -229,53 +565,946 @@ mod tests {
     fn test_shading_intersection() {
         let w = default_world();
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
-        let s = &w.objects[0];
+        let s = w.objects()[0].as_ref();
         let i = Intersection::new(s, 4.);
-        let comps = Computations::new(i, &r);
-        assert_near!(w.shade_hit(comps), Color::new(0.38066, 0.47583, 0.2855));
+        let comps = Computations::new(i, &r, &[i]);
+        assert_near!(
+            w.shade_hit(comps, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.38066, 0.47583, 0.2855)
+        );
     }
 
     #[test]
     fn test_shading_intersection_from_inside() {
         let mut w = default_world();
-        w.lights = vec![PointLight::new(point(0., 0.25, 0.), Color::new(1., 1., 1.))];
+        w.lights = vec![Box::new(PointLight::new(
+            point(0., 0.25, 0.),
+            Color::new(1., 1., 1.),
+        ))];
         let r = Ray::new(Point::new(0., 0., 0.), Vector::new(0., 0., 1.));
-        let s = &w.objects[1];
+        let s = w.objects()[1].as_ref();
         let i = Intersection::new(s, 0.5);
-        let comps = Computations::new(i, &r);
-        assert_near!(w.shade_hit(comps), Color::new(0.90498, 0.90498, 0.90498));
+        let comps = Computations::new(i, &r, &[i]);
+        assert_near!(
+            w.shade_hit(comps, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.90498, 0.90498, 0.90498)
+        );
     }
 
     #[test]
     fn test_color_when_ray_misses() {
         let w = default_world();
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 1., 0.));
-        assert_eq!(w.color_at(&r), Color::new(0., 0., 0.));
+        assert_eq!(
+            w.color_at(&r, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0., 0., 0.)
+        );
     }
 
     #[test]
     fn test_color_when_ray_hits() {
         let w = default_world();
         let r = Ray::new(Point::new(0., 0., -5.), Vector::new(0., 0., 1.));
-        assert_near!(w.color_at(&r), Color::new(0.38066, 0.47583, 0.2855));
+        assert_near!(
+            w.color_at(&r, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.38066, 0.47583, 0.2855)
+        );
     }
 
     #[test]
     fn test_color_with_intersection_behind_ray() {
         let mut w = default_world();
+        let objects = w
+            .objects()
+            .iter()
+            .map(|object| -> Box<dyn Shape> {
+                let mut s = Sphere::new();
+                s.set_transform(object.transform().matrix().clone())
+                    .unwrap();
+                s.set_material({
+                    let mut m = object.material();
+                    m.ambient = 1.0;
+                    m
+                });
+                Box::new(s)
+            })
+            .collect();
+        *w.objects_mut() = objects;
 
-        for object in &mut w.objects {
-            if let WorldObject::Sphere(s) = object {
-                let mut m = s.material();
-                m.ambient = 1.0;
-                s.set_material(m);
-            }
+        let r = Ray::new(Point::new(0., 0., 0.75), Vector::new(0., 0., -1.));
+        assert_near!(
+            w.color_at(&r, MAX_DEPTH, &mut Rng::new(0)),
+            w.objects()[1].material_at(point(0, 0, 0)).color
+        );
+    }
+
+    fn is_shadowed_from_first_light(w: &World, p: Point) -> bool {
+        w.is_shadowed(&w.lights[0].samples_at(p, &mut Rng::new(0))[0], p)
+    }
+
+    #[test]
+    fn test_no_shadow_when_nothing_collinear_with_point_and_light() {
+        let w = default_world();
+        assert!(!is_shadowed_from_first_light(&w, point(0, -10, 0)));
+    }
+
+    #[test]
+    fn test_shadow_when_object_between_point_and_light() {
+        let w = default_world();
+        assert!(is_shadowed_from_first_light(&w, point(10, 10, 10)));
+    }
+
+    #[test]
+    fn test_no_shadow_when_object_behind_light() {
+        let w = default_world();
+        assert!(!is_shadowed_from_first_light(&w, point(-20, -20, -20)));
+    }
+
+    #[test]
+    fn test_no_shadow_when_object_behind_point() {
+        let w = default_world();
+        assert!(!is_shadowed_from_first_light(&w, point(-2, -2, -2)));
+    }
+
+    #[test]
+    fn test_shade_hit_given_intersection_in_shadow() {
+        let mut w = World::new();
+        w.lights = vec![Box::new(PointLight::new(
+            point(0, 0, -10),
+            Color::new(1., 1., 1.),
+        ))];
+        w.add_object(Box::new(Sphere::new()));
+        w.add_object(Box::new({
+            let mut s = Sphere::new();
+            s.set_transform(transforms::translation(0., 0., 10.))
+                .unwrap();
+            s
+        }));
+        let r = Ray::new(point(0, 0, 5), vector(0, 0, 1));
+        let i = Intersection::new(w.objects()[1].as_ref(), 4.);
+        let comps = Computations::new(i, &r, &[i]);
+        assert_near!(
+            w.shade_hit(comps, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.1, 0.1, 0.1)
+        );
+    }
+
+    #[test]
+    fn test_hit_offsets_point() {
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let s = {
+            let mut s = Sphere::new();
+            s.set_transform(transforms::translation(0., 0., 1.))
+                .unwrap();
+            s
+        };
+        let i = Intersection::new(&s, 5.);
+        let comps = Computations::new(i, &r, &[i]);
+        assert!(comps.over_point.z < -EPSILON / 2.0);
+        assert!(comps.point.z > comps.over_point.z);
+    }
+
+    #[test]
+    fn test_intersect_world_with_plane() {
+        let mut w = default_world();
+        w.add_object(Box::new({
+            let mut p = Plane::new();
+            p.set_transform(transforms::translation(0., -1., 0.))
+                .unwrap();
+            p
+        }));
+        let r = Ray::new(point(0, 0, -3), vector(0, -1, 0));
+        let xs = w.intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].object, w.objects()[2].as_ref());
+        assert_eq!(xs[0].t, 1.0);
+    }
+
+    #[test]
+    fn test_hit_matches_intersect() {
+        let w = default_world();
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        assert_eq!(w.hit(&r), hit(&w.intersect(&r)));
+        let r = Ray::new(point(0, 0, 0), vector(0, 1, 0));
+        assert_eq!(w.hit(&r), hit(&w.intersect(&r)));
+        let r = Ray::new(point(0, 2, -5), vector(0, 0, 1));
+        assert_eq!(w.hit(&r), None);
+    }
+
+    #[test]
+    fn test_intersect_after_changing_objects() {
+        let mut w = default_world();
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        assert_eq!(w.intersect(&r).len(), 4);
+        w.objects_mut().remove(0);
+        assert_eq!(ts(w.intersect(&r)), vec![4.5, 5.5]);
+        w.add_object(Box::new({
+            let mut s = Sphere::new();
+            s.set_transform(transforms::translation(0., 0., 10.))
+                .unwrap();
+            s
+        }));
+        assert_eq!(w.intersect(&r).len(), 4);
+    }
+
+    fn reflective_plane(y: f32) -> Box<dyn Shape> {
+        let mut p = Plane::new();
+        p.set_material({
+            let mut m = Material::new();
+            m.reflective = 0.5;
+            m
+        });
+        p.set_transform(transforms::translation(0., y, 0.)).unwrap();
+        Box::new(p)
+    }
+
+    // The reflection tests are mirrored in y, like the default world's light.
+    fn ray_towards_plane() -> Ray {
+        Ray::new(point(0, 0, -3), vector(0.0, FRAC_1_SQRT_2, FRAC_1_SQRT_2))
+    }
+
+    #[test]
+    fn test_precompute_reflection_vector() {
+        let p = Plane::new();
+        let r = Ray::new(point(0, 1, -1), vector(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2));
+        let i = Intersection::new(&p, SQRT_2);
+        let comps = Computations::new(i, &r, &[i]);
+        assert_near!(comps.reflect_vec, vector(0.0, FRAC_1_SQRT_2, FRAC_1_SQRT_2));
+    }
+
+    #[test]
+    fn test_reflected_color_for_nonreflective_material() {
+        let w = default_world();
+        let r = Ray::new(point(0, 0, 0), vector(0, 0, 1));
+        let i = Intersection::new(w.objects()[1].as_ref(), 1.0);
+        let comps = Computations::new(i, &r, &[i]);
+        assert_eq!(
+            w.reflected_color(&comps, MAX_DEPTH, &mut Rng::new(0)),
+            Color::black()
+        );
+    }
+
+    #[test]
+    fn test_reflected_color_for_reflective_material() {
+        let mut w = default_world();
+        w.add_object(reflective_plane(1.0));
+        let r = ray_towards_plane();
+        let i = Intersection::new(w.objects()[2].as_ref(), SQRT_2);
+        let comps = Computations::new(i, &r, &[i]);
+        assert_near!(
+            w.reflected_color(&comps, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.19032, 0.2379, 0.14274),
+            1e-3
+        );
+    }
+
+    #[test]
+    fn test_shade_hit_with_reflective_material() {
+        let mut w = default_world();
+        w.add_object(reflective_plane(1.0));
+        let r = ray_towards_plane();
+        let i = Intersection::new(w.objects()[2].as_ref(), SQRT_2);
+        let comps = Computations::new(i, &r, &[i]);
+        assert_near!(
+            w.shade_hit(comps, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.87677, 0.92436, 0.82918),
+            1e-3
+        );
+    }
+
+    #[test]
+    fn test_color_at_with_mutually_reflective_surfaces() {
+        let mut w = World::new();
+        w.lights = vec![Box::new(PointLight::new(
+            point(0, 0, 0),
+            Color::new(1., 1., 1.),
+        ))];
+        for (y, rotate) in [(-1.0, false), (1.0, true)] {
+            w.add_object({
+                let mut p = Plane::new();
+                p.set_material({
+                    let mut m = Material::new();
+                    m.reflective = 1.0;
+                    m
+                });
+                let t = transforms::translation(0., y, 0.);
+                p.set_transform(if rotate {
+                    &t * &transforms::rotation_x(PI)
+                } else {
+                    t
+                })
+                .unwrap();
+                Box::new(p)
+            });
         }
+        let r = Ray::new(point(0, 0, 0), vector(0, 1, 0));
+        // Terminates instead of recursing forever.
+        w.color_at(&r, MAX_DEPTH, &mut Rng::new(0));
+    }
 
-        let r = Ray::new(Point::new(0., 0., 0.75), Vector::new(0., 0., -1.));
+    #[test]
+    fn test_reflected_color_at_maximum_depth() {
+        let mut w = default_world();
+        w.add_object(reflective_plane(1.0));
+        let r = ray_towards_plane();
+        let i = Intersection::new(w.objects()[2].as_ref(), SQRT_2);
+        let comps = Computations::new(i, &r, &[i]);
+        assert_eq!(
+            w.reflected_color(&comps, 0, &mut Rng::new(0)),
+            Color::black()
+        );
+    }
+
+    fn glass_sphere(transform: Matrix<4, 4>, refractive_index: f32) -> Sphere {
+        let mut s = Sphere::new();
+        s.set_transform(transform).unwrap();
+        s.set_material({
+            let mut m = Material::new();
+            m.transparency = 1.0;
+            m.refractive_index = refractive_index;
+            m
+        });
+        s
+    }
+
+    #[test]
+    fn test_find_n1_and_n2_at_various_intersections() {
+        let a = glass_sphere(transforms::scaling(2., 2., 2.), 1.5);
+        let b = glass_sphere(transforms::translation(0., 0., -0.25), 2.0);
+        let c = glass_sphere(transforms::translation(0., 0., 0.25), 2.5);
+        let r = Ray::new(point(0, 0, -4), vector(0, 0, 1));
+        let xs = vec![
+            Intersection::new(&a, 2.0),
+            Intersection::new(&b, 2.75),
+            Intersection::new(&c, 3.25),
+            Intersection::new(&b, 4.75),
+            Intersection::new(&c, 5.25),
+            Intersection::new(&a, 6.0),
+        ];
+        let expected = [
+            (1.0, 1.5),
+            (1.5, 2.0),
+            (2.0, 2.5),
+            (2.5, 2.5),
+            (2.5, 1.5),
+            (1.5, 1.0),
+        ];
+        for (x, (n1, n2)) in xs.iter().zip(expected) {
+            let comps = Computations::new(*x, &r, &xs);
+            assert_eq!((comps.n1, comps.n2), (n1, n2));
+        }
+    }
+
+    #[test]
+    fn test_under_point_is_below_surface() {
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let s = glass_sphere(transforms::translation(0., 0., 1.), 1.5);
+        let i = Intersection::new(&s, 5.);
+        let comps = Computations::new(i, &r, &[i]);
+        assert!(comps.under_point.z > EPSILON / 2.0);
+        assert!(comps.point.z < comps.under_point.z);
+    }
+
+    #[test]
+    fn test_refracted_color_with_opaque_surface() {
+        let w = default_world();
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let s = w.objects()[0].as_ref();
+        let xs = [Intersection::new(s, 4.0), Intersection::new(s, 6.0)];
+        let comps = Computations::new(xs[0], &r, &xs);
+        assert_eq!(
+            w.refracted_color(&comps, MAX_DEPTH, &mut Rng::new(0)),
+            Color::black()
+        );
+    }
+
+    #[test]
+    fn test_refracted_color_at_maximum_depth() {
+        let s = glass_sphere(Matrix::identity(), 1.5);
+        let w = default_world();
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let xs = [Intersection::new(&s, 4.0), Intersection::new(&s, 6.0)];
+        let comps = Computations::new(xs[0], &r, &xs);
+        assert_eq!(
+            w.refracted_color(&comps, 0, &mut Rng::new(0)),
+            Color::black()
+        );
+    }
+
+    #[test]
+    fn test_refracted_color_under_total_internal_reflection() {
+        let s = glass_sphere(Matrix::identity(), 1.5);
+        let w = default_world();
+        let r = Ray::new(point(0.0, 0.0, FRAC_1_SQRT_2), vector(0, 1, 0));
+        let xs = [
+            Intersection::new(&s, -FRAC_1_SQRT_2),
+            Intersection::new(&s, FRAC_1_SQRT_2),
+        ];
+        let comps = Computations::new(xs[1], &r, &xs);
+        assert_eq!(
+            w.refracted_color(&comps, MAX_DEPTH, &mut Rng::new(0)),
+            Color::black()
+        );
+    }
+
+    fn world_with_glass_floor(reflective: f32) -> World {
+        let mut w = default_world();
+        w.add_object(Box::new({
+            let mut p = Plane::new();
+            p.set_transform(transforms::translation(0., 1., 0.))
+                .unwrap();
+            p.set_material({
+                let mut m = Material::new();
+                m.reflective = reflective;
+                m.transparency = 0.5;
+                m.refractive_index = 1.5;
+                m
+            });
+            p
+        }));
+        w.add_object(Box::new({
+            let mut s = Sphere::new();
+            s.set_transform(transforms::translation(0., 3.5, -0.5))
+                .unwrap();
+            s.set_material({
+                let mut m = Material::new();
+                m.color = Color::new(1., 0., 0.);
+                m.ambient = 0.5;
+                m
+            });
+            s
+        }));
+        w
+    }
+
+    #[test]
+    fn test_shade_hit_with_transparent_material() {
+        let w = world_with_glass_floor(0.0);
+        let r = ray_towards_plane();
+        let xs = [Intersection::new(w.objects()[2].as_ref(), SQRT_2)];
+        let comps = Computations::new(xs[0], &r, &xs);
+        assert_near!(
+            w.shade_hit(comps, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.93642, 0.68642, 0.68642),
+            1e-3
+        );
+    }
+
+    #[test]
+    fn test_shade_hit_with_reflective_transparent_material() {
+        let w = world_with_glass_floor(0.5);
+        let r = ray_towards_plane();
+        let xs = [Intersection::new(w.objects()[2].as_ref(), SQRT_2)];
+        let comps = Computations::new(xs[0], &r, &xs);
+        assert_near!(
+            w.shade_hit(comps, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.93391, 0.69643, 0.69243),
+            1e-3
+        );
+    }
+
+    #[test]
+    fn test_schlick_under_total_internal_reflection() {
+        let s = glass_sphere(Matrix::identity(), 1.5);
+        let r = Ray::new(point(0.0, 0.0, FRAC_1_SQRT_2), vector(0, 1, 0));
+        let xs = [
+            Intersection::new(&s, -FRAC_1_SQRT_2),
+            Intersection::new(&s, FRAC_1_SQRT_2),
+        ];
+        let comps = Computations::new(xs[1], &r, &xs);
+        assert_eq!(comps.schlick(), 1.0);
+    }
+
+    #[test]
+    fn test_schlick_with_perpendicular_viewing_angle() {
+        let s = glass_sphere(Matrix::identity(), 1.5);
+        let r = Ray::new(point(0, 0, 0), vector(0, 1, 0));
+        let xs = [Intersection::new(&s, -1.0), Intersection::new(&s, 1.0)];
+        let comps = Computations::new(xs[1], &r, &xs);
+        assert_near!(comps.schlick(), 0.04);
+    }
+
+    #[test]
+    fn test_schlick_with_small_angle_and_n2_greater_than_n1() {
+        let s = glass_sphere(Matrix::identity(), 1.5);
+        let r = Ray::new(point(0.0, 0.99, -2.0), vector(0, 0, 1));
+        let xs = [Intersection::new(&s, 1.8589)];
+        let comps = Computations::new(xs[0], &r, &xs);
+        assert_near!(comps.schlick(), 0.48873, 1e-3);
+    }
+
+    /// The fraction of the samples of `light` that are not in shadow at `p`.
+    fn visible_fraction(w: &World, light: &dyn Light, p: Point, rng: &mut Rng) -> f32 {
+        let samples = w.light_samples(light, p, p, rng);
+        let visible = samples
+            .iter()
+            .filter(|sample| sample.intensity != Color::black())
+            .count();
+        visible as f32 / samples.len() as f32
+    }
+
+    #[test]
+    fn test_point_light_intensity_at_point() {
+        let w = default_world();
+        // Mirrored in y, like the default world's light.
+        let cases = [
+            (point(0.0, -1.0001, 0.0), 1.0),
+            (point(-1.0001, 0.0, 0.0), 1.0),
+            (point(0.0, 0.0, -1.0001), 1.0),
+            (point(0.0, 0.0, 1.0001), 0.0),
+            (point(1.0001, 0.0, 0.0), 0.0),
+            (point(0.0, 1.0001, 0.0), 0.0),
+            (point(0, 0, 0), 0.0),
+        ];
+        for (p, intensity) in cases {
+            assert_eq!(
+                visible_fraction(&w, w.lights[0].as_ref(), p, &mut Rng::new(0)),
+                intensity
+            );
+        }
+    }
+
+    fn square_area_light(z: f32) -> AreaLight {
+        let mut light = AreaLight::new(
+            point(-0.5, -0.5, z),
+            vector(1, 0, 0),
+            2,
+            vector(0, 1, 0),
+            2,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        light.jitter = false;
+        light
+    }
+
+    #[test]
+    fn test_area_light_intensity_at_point() {
+        let w = default_world();
+        let light = square_area_light(-5.0);
+        let cases = [
+            (point(0, 0, 2), 0.0),
+            (point(1, -1, 2), 0.25),
+            (point(1.5, 0.0, 2.0), 0.5),
+            (point(1.25, 1.25, 3.0), 0.75),
+            (point(0, 0, -2), 1.0),
+        ];
+        for (p, intensity) in cases {
+            assert_eq!(visible_fraction(&w, &light, p, &mut Rng::new(0)), intensity);
+        }
+    }
+
+    #[test]
+    fn test_phong_samples_area_light() {
+        let w = World::new();
+        let light = square_area_light(-5.0);
+        let mut m = Material::new();
+        m.ambient = 0.1;
+        m.diffuse = 0.9;
+        m.specular = 0.0;
+        let eye = point(0, 0, -5);
+        let cases = [
+            (point(0, 0, -1), Color::new(0.9965, 0.9965, 0.9965)),
+            (
+                point(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+                Color::new(0.62318, 0.62318, 0.62318),
+            ),
+        ];
+        for (p, c) in cases {
+            let eye_vec = (eye - p).normalized();
+            let normal_vec = p - point(0, 0, 0);
+            let samples = w.light_samples(&light, p, p, &mut Rng::new(0));
+            let result = lighting::phong(&m, &light, &samples, eye_vec, normal_vec);
+            assert_near!(result, c, 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_jittered_area_light_gives_soft_shadow_edge() {
+        let w = default_world();
+        let light = AreaLight::new(
+            point(-0.5, -0.5, -5.0),
+            vector(1, 0, 0),
+            8,
+            vector(0, 1, 0),
+            8,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        assert!(light.jitter);
+        // Half of the light is hidden from this point by the sphere.
+        let mut rng = Rng::new(0);
+        let fractions: Vec<f32> = (0..10)
+            .map(|_| visible_fraction(&w, &light, point(1.5, 0.0, 2.0), &mut rng))
+            .collect();
+        for &fraction in &fractions {
+            assert!(0.0 < fraction && fraction < 1.0, "{}", fraction);
+        }
+        // Each call samples different points of the cells.
+        assert!(fractions.iter().any(|&f| f != fractions[0]));
+    }
+
+    #[test]
+    fn test_directional_light_is_shadowed_by_anything_in_its_way() {
+        let mut w = default_world();
+        w.lights = vec![Box::new(DirectionalLight::new(
+            vector(0, 1, 0),
+            Color::new(1., 1., 1.),
+        ))];
+        assert_eq!(
+            visible_fraction(
+                &w,
+                w.lights[0].as_ref(),
+                point(0, 1000, 0),
+                &mut Rng::new(0)
+            ),
+            0.0
+        );
+        assert_eq!(
+            visible_fraction(&w, w.lights[0].as_ref(), point(0, -2, 0), &mut Rng::new(0)),
+            1.0
+        );
+        assert_eq!(
+            visible_fraction(
+                &w,
+                w.lights[0].as_ref(),
+                point(5, 1000, 0),
+                &mut Rng::new(0)
+            ),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_shade_hit_outside_spot_light_cone_is_ambient() {
+        let mut w = default_world();
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let xs = w.intersect(&r);
+        let comps = Computations::new(xs[0], &r, &xs);
+        let lit = w.shade_hit(comps, MAX_DEPTH, &mut Rng::new(0));
+
+        w.lights = vec![Box::new(SpotLight::new(
+            point(-10, -10, -10),
+            vector(1, 1, 1),
+            PI / 36.0,
+            PI / 18.0,
+            Color::new(1., 1., 1.),
+        ))];
+        let xs = w.intersect(&r);
+        let comps = Computations::new(xs[0], &r, &xs);
+        assert_near!(w.shade_hit(comps, MAX_DEPTH, &mut Rng::new(0)), lit);
+
+        w.lights = vec![Box::new(SpotLight::new(
+            point(-10, -10, -10),
+            vector(-1, -1, -1),
+            PI / 36.0,
+            PI / 18.0,
+            Color::new(1., 1., 1.),
+        ))];
+        let xs = w.intersect(&r);
+        let comps = Computations::new(xs[0], &r, &xs);
         assert_near!(
-            w.color_at(&r),
-            w.objects[1].material_at(point(0, 0, 0)).color
+            w.shade_hit(comps, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.1 * 0.8, 0.1, 0.1 * 0.6)
         );
     }
+
+    #[test]
+    fn test_direct_light_from_point_light() {
+        let mut w = World::new();
+        w.lights
+            .push(Box::new(PointLight::new(point(0, 0, -10), Color::white())));
+        w.add_object(Box::new(Sphere::new()));
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        let xs = w.intersect(&r);
+        let comps = Computations::new(xs[0], &r, &xs);
+        let mut rng = Rng::new(0);
+        let expected = 0.9 / PI;
+        assert_near!(
+            w.direct_light(&comps, &mut rng),
+            Color::new(expected, expected, expected)
+        );
+    }
+
+    /// Averages `direct_light` at the top of a floor lit by `light`.
+    fn mean_direct_light(light: AreaLight) -> f32 {
+        let mut w = World::new();
+        w.lights.push(Box::new(light));
+        w.add_object(Box::new(Plane::new()));
+        let r = Ray::new(point(0, 1, 0), vector(0, -1, 0));
+        let xs = w.intersect(&r);
+        let comps = Computations::new(xs[0], &r, &xs);
+        let mut rng = Rng::new(0);
+        let count = 4000;
+        let mut sum = 0.0;
+        for _ in 0..count {
+            sum += w.direct_light(&comps, &mut rng).red;
+        }
+        sum / count as f32
+    }
+
+    #[test]
+    fn test_direct_light_from_huge_area_light() {
+        // A light covering the sky gives an irradiance of pi times its
+        // radiance, of which the albedo is reflected.
+        let light = AreaLight::with_radiance(
+            point(-1000, 1, -1000),
+            vector(2000, 0, 0),
+            1,
+            vector(0, 0, 2000),
+            1,
+            Color::white(),
+        )
+        .unwrap();
+        assert_near!(mean_direct_light(light), 0.9, 1e-2);
+    }
+
+    #[test]
+    fn test_direct_light_from_small_area_light() {
+        // A small light far away is close to a point light dimmed by the
+        // inverse square of the distance.
+        let radiance = 1000.0;
+        let light = AreaLight::with_radiance(
+            point(-0.1, 5.0, -0.1),
+            vector(0.2, 0.0, 0.0),
+            1,
+            vector(0.0, 0.0, 0.2),
+            1,
+            Color::new(radiance, radiance, radiance),
+        )
+        .unwrap();
+        let irradiance = radiance * 0.04 / 25.0;
+        assert_near!(mean_direct_light(light), 0.9 / PI * irradiance, 1e-2);
+    }
+
+    fn emissive(emission: Color) -> Material {
+        let mut m = Material::new();
+        m.emission = emission;
+        m
+    }
+
+    #[test]
+    fn test_shade_hit_adds_emission() {
+        let mut w = World::new();
+        let mut s = Sphere::new();
+        s.set_material(emissive(Color::new(0.5, 1.0, 2.0)));
+        w.add_object(Box::new(s));
+        let r = Ray::new(point(0, 0, -5), vector(0, 0, 1));
+        assert_eq!(
+            w.color_at(&r, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.5, 1.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn test_emitters_are_found_in_groups() {
+        let mut w = default_world();
+        assert_eq!(w.emitters().total_area(), 0.0);
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::new()));
+        g.add_child(Box::new({
+            let mut t = Triangle::new(point(0, 0, 0), point(1, 0, 0), point(0, 1, 0));
+            t.set_material(emissive(Color::white()));
+            t
+        }));
+        w.add_object(Box::new(g));
+        let mut s = Sphere::new();
+        s.set_material(emissive(Color::white()));
+        w.add_object(Box::new(s));
+        assert_eq!(w.emitters().paths, vec![vec![2, 1], vec![3]]);
+        assert_near!(w.emitters().total_area(), 0.5 + 4.0 * PI);
+        let picked = w.emitters().pick(w.objects(), 0.01);
+        assert_eq!(picked.area(), Some(0.5));
+        let picked = w.emitters().pick(w.objects(), 0.99);
+        assert!(std::ptr::addr_eq(picked, w.objects()[3].as_ref()));
+    }
+
+    /// Averages `direct_light` at the top of a floor in the world.
+    fn mean_direct_light_on_floor(mut w: World) -> f32 {
+        w.add_object(Box::new(Plane::new()));
+        let r = Ray::new(point(0, 1, 0), vector(0, -1, 0));
+        let xs = w.intersect(&r);
+        let comps = Computations::new(hit(&xs).unwrap(), &r, &xs);
+        let mut rng = Rng::new(0);
+        let count = 20000;
+        let mut sum = 0.0;
+        for _ in 0..count {
+            sum += w.direct_light(&comps, &mut rng).red;
+        }
+        sum / count as f32
+    }
+
+    #[test]
+    fn test_direct_light_from_emissive_sphere() {
+        // A sphere of radius r at distance d right above gives an irradiance
+        // of pi * (r / d)^2 times its radiance.
+        let mut w = World::new();
+        let mut s = Sphere::new();
+        s.set_transform(transforms::translation(0.0, 3.0, 0.0))
+            .unwrap();
+        s.set_material(emissive(Color::white()));
+        w.add_object(Box::new(s));
+        let irradiance = PI / 9.0;
+        assert_near!(mean_direct_light_on_floor(w), 0.9 / PI * irradiance, 3e-3);
+    }
+
+    #[test]
+    fn test_direct_light_from_emissive_sphere_in_csg() {
+        // The spheres in the union are sampled as lights too, but points on
+        // the inner one are hidden inside the outer one.
+        let mut w = World::new();
+        let mut s = Sphere::new();
+        s.set_transform(transforms::translation(0.0, 3.0, 0.0))
+            .unwrap();
+        s.set_material(emissive(Color::white()));
+        w.add_object(Box::new(s));
+        let mut left = Sphere::new();
+        left.set_transform(transforms::translation(3.0, 3.0, 0.0))
+            .unwrap();
+        left.set_material(emissive(Color::white()));
+        let mut right = Sphere::new();
+        right
+            .set_transform(
+                &transforms::translation(3.0, 3.0, 0.0) * &transforms::scaling(0.5, 0.5, 0.5),
+            )
+            .unwrap();
+        right.set_material(emissive(Color::white()));
+        w.add_object(Box::new(Csg::new(
+            CsgOperation::Union,
+            Box::new(left),
+            Box::new(right),
+        )));
+        assert_eq!(w.emitters().paths, vec![vec![0], vec![1, 0], vec![1, 1]]);
+        // Off to the side, a sphere's irradiance is also scaled by the
+        // cosine to its center.
+        let irradiance = PI / 9.0 + PI / 18.0 * FRAC_1_SQRT_2;
+        assert_near!(mean_direct_light_on_floor(w), 0.9 / PI * irradiance, 3e-3);
+    }
+
+    #[test]
+    fn test_no_direct_light_from_empty_csg() {
+        // Nothing is left of an emissive sphere intersected with a sphere it
+        // does not touch, even though its points are still sampled.
+        let mut w = World::new();
+        let mut left = Sphere::new();
+        left.set_transform(transforms::translation(0.0, 3.0, 0.0))
+            .unwrap();
+        left.set_material(emissive(Color::white()));
+        let mut right = Sphere::new();
+        right
+            .set_transform(transforms::translation(0.0, 10.0, 0.0))
+            .unwrap();
+        w.add_object(Box::new(Csg::new(
+            CsgOperation::Intersection,
+            Box::new(left),
+            Box::new(right),
+        )));
+        assert_eq!(w.emitters().paths, vec![vec![0, 0]]);
+        assert_eq!(mean_direct_light_on_floor(w), 0.0);
+    }
+
+    #[test]
+    fn test_direct_light_from_emissive_mesh() {
+        // Like a huge area light, a huge glowing ceiling reflects the albedo
+        // times its radiance.
+        let mut w = World::new();
+        let mut g = Group::new();
+        for (a, b, c) in [
+            (
+                point(-1000, 2, -1000),
+                point(1000, 2, -1000),
+                point(1000, 2, 1000),
+            ),
+            (
+                point(-1000, 2, -1000),
+                point(1000, 2, 1000),
+                point(-1000, 2, 1000),
+            ),
+        ] {
+            let mut t = Triangle::new(a, b, c);
+            t.set_material(emissive(Color::white()));
+            g.add_child(Box::new(t));
+        }
+        w.add_object(Box::new(g));
+        assert_near!(mean_direct_light_on_floor(w), 0.9, 1e-2);
+    }
+
+    #[test]
+    fn test_color_when_ray_misses_is_background() {
+        let mut w = default_world();
+        let r = Ray::new(point(0, 0, -5), vector(0, 1, 0));
+        assert_eq!(w.color_at(&r, MAX_DEPTH, &mut Rng::new(0)), Color::black());
+        w.background = Background::Gradient {
+            bottom: Color::black(),
+            top: Color::new(0.2, 0.4, 0.8),
+        };
+        assert_eq!(
+            w.color_at(&r, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.2, 0.4, 0.8)
+        );
+    }
+
+    #[test]
+    fn test_reflected_and_refracted_misses_see_background() {
+        let mut w = World::new();
+        w.background = Background::Color(Color::new(0.0, 0.0, 1.0));
+        let mut mirror = Plane::new();
+        mirror.set_material({
+            let mut m = Material::new();
+            m.color = Color::black();
+            m.reflective = 1.0;
+            m
+        });
+        w.add_object(Box::new(mirror));
+        let r = Ray::new(point(0, 1, -1), vector(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2));
+        assert_near!(
+            w.color_at(&r, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.0, 0.0, 1.0)
+        );
+
+        let mut w = World::new();
+        w.background = Background::Color(Color::new(0.0, 0.0, 1.0));
+        let mut glass = Plane::new();
+        glass.set_material({
+            let mut m = Material::new();
+            m.color = Color::black();
+            m.transparency = 1.0;
+            m
+        });
+        w.add_object(Box::new(glass));
+        let r = Ray::new(point(0, 1, 0), vector(0, -1, 0));
+        assert_near!(
+            w.color_at(&r, MAX_DEPTH, &mut Rng::new(0)),
+            Color::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_shade_hit_with_environment_light() {
+        let mut w = World::new();
+        let sky = EnvironmentMap::new(Canvas::new(32, 16, Color::white())).unwrap();
+        let mut light = EnvironmentLight::new(sky);
+        light.set_samples(2000).unwrap();
+        w.lights.push(Box::new(light));
+        let mut floor = Plane::new();
+        let mut m = Material::new();
+        m.ambient = 0.0;
+        m.specular = 0.0;
+        floor.set_material(m);
+        w.add_object(Box::new(floor));
+        let r = Ray::new(point(0, 1, -1), vector(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2));
+        // The half of the sky below the floor adds nothing, shadowed or not.
+        let c = w.color_at(&r, MAX_DEPTH, &mut Rng::new(0));
+        assert_near!(c.red, 0.9, 1e-2);
+
+        let mut blocker = Sphere::new();
+        blocker
+            .set_transform(transforms::translation(0.0, 2.0, 0.0))
+            .unwrap();
+        w.add_object(Box::new(blocker));
+        // The sphere hides a cone of 30 degrees around the normal, which
+        // carries sin^2(30) of the cosine weighted light.
+        let shadowed = w.color_at(&r, MAX_DEPTH, &mut Rng::new(0));
+        assert_near!(shadowed.red, 0.9 * 0.75, 1e-2);
+    }
 }